@@ -1,3 +1,8 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::num::ParseIntError;
+use std::sync::{mpsc, Mutex, PoisonError};
+
 #[allow(unused)]
 const TEST_INPUT: &str = r#"7 6 4 2 1
 1 2 7 8 9
@@ -9,76 +14,724 @@ const TEST_INPUT: &str = r#"7 6 4 2 1
 #[allow(unused)]
 const INPUT_PATH: &str = "input.txt";
 
-fn parse_input(input: &str) -> Vec<Vec<i32>> {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Increasing,
+    Decreasing,
+    // direction is decided by the first non-zero step of the report
+    Either,
+    NonMonotonic,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct UnknownDirection(String);
+
+impl std::fmt::Display for UnknownDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unknown direction: {} (expected increasing, decreasing, either or non-monotonic)",
+            self.0
+        )
+    }
+}
+
+impl std::str::FromStr for Direction {
+    type Err = UnknownDirection;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "increasing" | "inc" => Ok(Direction::Increasing),
+            "decreasing" | "dec" => Ok(Direction::Decreasing),
+            "either" => Ok(Direction::Either),
+            "non-monotonic" | "any" => Ok(Direction::NonMonotonic),
+            _ => Err(UnknownDirection(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SafetyPolicy {
+    min_step: u64,
+    max_step: u64,
+    direction: Direction,
+    // how many zero steps are let through regardless of `min_step`
+    equal_tolerance: usize,
+    max_removals: usize,
+}
+
+impl SafetyPolicy {
+    const PART_1: SafetyPolicy = SafetyPolicy {
+        min_step: 1,
+        max_step: 3,
+        direction: Direction::Either,
+        equal_tolerance: 0,
+        max_removals: 0,
+    };
+
+    const PART_2: SafetyPolicy = SafetyPolicy {
+        max_removals: 1,
+        ..SafetyPolicy::PART_1
+    };
+
+    fn set(&mut self, key: &str, value: &str) {
+        let parse_num = |value: &str| -> u64 {
+            value
+                .parse()
+                .unwrap_or_else(|err| panic!("Invalid value for {key}: {value} ({:?})", err))
+        };
+
+        match key {
+            "min-step" => self.min_step = parse_num(value),
+            "max-step" => self.max_step = parse_num(value),
+            "direction" => {
+                self.direction = value
+                    .parse()
+                    .unwrap_or_else(|err| panic!("Invalid value for {key}: {err}"))
+            }
+            "equal-tolerance" => self.equal_tolerance = parse_num(value) as usize,
+            "max-removals" => self.max_removals = parse_num(value) as usize,
+            _ => panic!("Unknown policy option: {}", key),
+        }
+    }
+
+    // one `key = value` per line, `#` starts a comment; keys it doesn't
+    // mention keep their current value
+    fn apply_config(&mut self, config: &str) {
+        for line in config.lines() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .unwrap_or_else(|| panic!("Invalid config line: {}", line));
+            self.set(key.trim(), value.trim());
+        }
+    }
+}
+
+fn parse_report(line: &str) -> Result<Vec<i64>, ParseIntError> {
+    line.split_whitespace()
+        .map(|num| num.trim().parse())
+        .collect()
+}
+
+fn parse_input(input: &str) -> Vec<Vec<i64>> {
     input
         .lines()
+        .filter(|line| !line.trim().is_empty())
         .map(|line| {
-            line.split_whitespace()
-                .map(|num| num.trim().parse().unwrap())
-                .collect()
+            parse_report(line).unwrap_or_else(|err| panic!("Invalid report {line:?}: {err}"))
         })
         .collect()
 }
 
-fn is_good(seq: &[i32]) -> bool {
-    let diff = seq[1].abs_diff(seq[0]);
-    if !(1..=3).contains(&diff) {
-        return false;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ViolationKind {
+    StepTooSmall,
+    StepTooLarge,
+    ZeroStep,
+    DirectionChange,
+}
+
+impl ViolationKind {
+    fn describe(self) -> &'static str {
+        match self {
+            ViolationKind::StepTooSmall => "step too small",
+            ViolationKind::StepTooLarge => "step too large",
+            ViolationKind::ZeroStep => "zero step",
+            ViolationKind::DirectionChange => "direction change",
+        }
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Violation {
+    // index of the level that broke the rule, i.e. the right one of the pair
+    index: usize,
+    kind: ViolationKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Verdict {
+    Safe,
+    Dampened(Vec<usize>),
+    Unsafe,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Diagnosis {
+    verdict: Verdict,
+    violation: Option<Violation>,
+}
+
+// Reports with fewer than two levels have no steps and are always safe,
+// a two-level report is judged on its single step alone. Differences are
+// taken with `abs_diff`, so any pair of i64 levels is compared exactly.
+fn first_violation(seq: &[i64], policy: &SafetyPolicy) -> Option<Violation> {
+    let mut equal_steps = 0;
+    let mut is_increasing = match policy.direction {
+        Direction::Increasing => Some(true),
+        Direction::Decreasing => Some(false),
+        Direction::Either | Direction::NonMonotonic => None,
+    };
 
-    let is_increasing = (seq[1] - seq[0]) > 0;
-    for tuple in seq.windows(2) {
+    for (i, tuple) in seq.windows(2).enumerate() {
+        let violation = |kind| Some(Violation { index: i + 1, kind });
         let (left, right) = (tuple[0], tuple[1]);
         let diff = right.abs_diff(left);
-        if !(1..=3).contains(&diff) || ((right - left) > 0) != is_increasing {
-            return false;
+        if diff == 0 && equal_steps < policy.equal_tolerance {
+            equal_steps += 1;
+            continue;
+        }
+        if diff < policy.min_step {
+            return violation(if diff == 0 {
+                ViolationKind::ZeroStep
+            } else {
+                ViolationKind::StepTooSmall
+            });
+        }
+        if diff > policy.max_step {
+            return violation(ViolationKind::StepTooLarge);
+        }
+        if diff == 0 || policy.direction == Direction::NonMonotonic {
+            continue;
+        }
+
+        let increasing = right > left;
+        if *is_increasing.get_or_insert(increasing) != increasing {
+            return violation(ViolationKind::DirectionChange);
         }
     }
 
-    true
+    None
 }
 
-fn part_1(input: &str) -> usize {
-    let lists = parse_input(input);
+fn is_good(seq: &[i64], policy: &SafetyPolicy) -> bool {
+    first_violation(seq, policy).is_none()
+}
 
-    lists.iter().filter(|&list| is_good(list)).count()
+// `Some(1)` when the step is only allowed by the equality tolerance
+fn step_cost(
+    left: i64,
+    right: i64,
+    policy: &SafetyPolicy,
+    increasing: Option<bool>,
+) -> Option<usize> {
+    let diff = right.abs_diff(left);
+    if diff == 0 {
+        return Some(if policy.min_step == 0 { 0 } else { 1 });
+    }
+
+    let direction_ok = increasing.is_none_or(|increasing| (right > left) == increasing);
+    ((policy.min_step..=policy.max_step).contains(&diff) && direction_ok).then_some(0)
 }
 
-fn part_2(input: &str) -> usize {
-    let lists = parse_input(input);
+fn dampen_directed(
+    seq: &[i64],
+    policy: &SafetyPolicy,
+    increasing: Option<bool>,
+) -> Option<Vec<usize>> {
+    // a single level is always safe, so there is never a reason to remove
+    // every level, and a report has at most n - 1 equal steps to tolerate
+    let n = seq.len();
+    if n == 0 {
+        return Some(vec![]);
+    }
+    let (k, t) = (
+        policy.max_removals.min(n - 1),
+        policy.equal_tolerance.min(n - 1),
+    );
 
-    let mut cnt = 0;
+    // a state is "level i is kept and e equal steps were tolerated so far";
+    // `best` stores the fewest levels removed before i to get there, and the
+    // previous kept state. Having removed more never helps later on
+    let state = |i: usize, e: usize| i * (t + 1) + e;
+    let mut best: Vec<Option<(usize, Option<usize>)>> = vec![None; n * (t + 1)];
+    for i in 0..=k {
+        best[state(i, 0)] = Some((i, None));
+    }
 
-    for list in lists.iter() {
-        if is_good(list) {
-            cnt += 1;
-            continue;
+    // only the next k + 1 levels can follow a kept one, so the whole pass
+    // takes O(n·k·(t + 1)) steps, O(n·k) without an equality tolerance
+    for i in 0..n {
+        for e in 0..=t {
+            let Some((removed, _)) = best[state(i, e)] else {
+                continue;
+            };
+            for j in i + 1..n.min(i + 2 + k - removed) {
+                let Some(cost) = step_cost(seq[i], seq[j], policy, increasing) else {
+                    continue;
+                };
+                if e + cost > t {
+                    continue;
+                }
+                let removed = removed + j - i - 1;
+                let next = &mut best[state(j, e + cost)];
+                if next.is_none_or(|(known, _)| removed < known) {
+                    *next = Some((removed, Some(state(i, e))));
+                }
+            }
+        }
+    }
+
+    let mut last = (0..best.len())
+        .filter_map(|s| {
+            let (removed, _) = best[s]?;
+            Some((removed + n - 1 - s / (t + 1), s))
+        })
+        .filter(|&(removed, _)| removed <= k)
+        .min()?
+        .1;
+
+    let mut kept = vec![false; n];
+    loop {
+        kept[last / (t + 1)] = true;
+        match best[last].unwrap().1 {
+            Some(previous) => last = previous,
+            None => break,
         }
+    }
+
+    Some((0..n).filter(|&i| !kept[i]).collect())
+}
+
+// the smallest set of level indices whose removal makes the report safe
+fn dampen(seq: &[i64], policy: &SafetyPolicy) -> Option<Vec<usize>> {
+    let directions: &[Option<bool>] = match policy.direction {
+        Direction::Increasing => &[Some(true)],
+        Direction::Decreasing => &[Some(false)],
+        Direction::Either => &[Some(true), Some(false)],
+        Direction::NonMonotonic => &[None],
+    };
+
+    directions
+        .iter()
+        .filter_map(|&increasing| dampen_directed(seq, policy, increasing))
+        .min_by_key(|removed| removed.len())
+}
+
+fn is_safe(seq: &[i64], policy: &SafetyPolicy) -> bool {
+    is_good(seq, policy) || dampen(seq, policy).is_some()
+}
+
+fn diagnose(seq: &[i64], policy: &SafetyPolicy) -> Diagnosis {
+    let violation = first_violation(seq, policy);
+    let verdict = match (violation, dampen(seq, policy)) {
+        (None, _) => Verdict::Safe,
+        (Some(_), Some(removed)) => Verdict::Dampened(removed),
+        (Some(_), None) => Verdict::Unsafe,
+    };
+
+    Diagnosis { verdict, violation }
+}
+
+fn render_diagnosis(seq: &[i64], diagnosis: &Diagnosis) -> String {
+    const RED: &str = "\x1b[1;31m";
+    const STRUCK: &str = "\x1b[2;9m";
+    const RESET: &str = "\x1b[0m";
 
-        for i in 0..list.len() {
-            let mut new_list = list.clone();
-            new_list.remove(i);
-            if is_good(&new_list) {
-                cnt += 1;
-                break;
+    let removed: &[usize] = match &diagnosis.verdict {
+        Verdict::Dampened(removed) => removed,
+        _ => &[],
+    };
+    let levels = seq
+        .iter()
+        .enumerate()
+        .map(|(i, level)| {
+            if diagnosis
+                .violation
+                .is_some_and(|violation| violation.index == i)
+            {
+                format!("{RED}{level}{RESET}")
+            } else if removed.contains(&i) {
+                format!("{STRUCK}{level}{RESET}")
+            } else {
+                level.to_string()
             }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let status = match &diagnosis.verdict {
+        Verdict::Safe => "safe".to_string(),
+        Verdict::Dampened(removed) => format!("safe after removing level(s) at {:?}", removed),
+        Verdict::Unsafe => "unsafe".to_string(),
+    };
+    match diagnosis.violation {
+        Some(violation) => format!(
+            "{levels}  -> {status} ({} at index {})",
+            violation.kind.describe(),
+            violation.index
+        ),
+        None => format!("{levels}  -> {status}"),
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Tally {
+    safe: usize,
+    dampened: usize,
+    rejected: usize,
+    // lines that aren't a list of numbers
+    malformed: usize,
+}
+
+impl Tally {
+    fn add(&mut self, seq: &[i64], policy: &SafetyPolicy) {
+        if is_good(seq, policy) {
+            self.safe += 1;
+        } else if dampen(seq, policy).is_some() {
+            self.dampened += 1;
+        } else {
+            self.rejected += 1;
         }
     }
 
-    cnt
+    fn merge(self, other: Tally) -> Tally {
+        Tally {
+            safe: self.safe + other.safe,
+            dampened: self.dampened + other.dampened,
+            rejected: self.rejected + other.rejected,
+            malformed: self.malformed + other.malformed,
+        }
+    }
 }
 
-fn main() {
-    let input = std::fs::read_to_string(INPUT_PATH).unwrap_or_else(|err| {
+type SharedReceiver<T> = Mutex<Option<mpsc::Receiver<T>>>;
+
+// A worker that panics drops the shared receiver on its way out, so the
+// producer gets an error from `send` instead of blocking on a full channel
+// that nobody is reading any more.
+struct CloseOnPanic<'a, T>(&'a SharedReceiver<T>);
+
+impl<T> Drop for CloseOnPanic<'_, T> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.0.lock().unwrap_or_else(PoisonError::into_inner).take();
+        }
+    }
+}
+
+// Lines are handed to the workers in small batches over a bounded channel,
+// so only a few batches are ever held in memory at once.
+fn classify_stream<R: BufRead>(reader: R, policy: &SafetyPolicy, workers: usize) -> Tally {
+    const BATCH_SIZE: usize = 256;
+
+    let (sender, receiver) = mpsc::sync_channel::<Vec<String>>(workers * 2);
+    let receiver: SharedReceiver<Vec<String>> = Mutex::new(Some(receiver));
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let _close = CloseOnPanic(&receiver);
+                    let mut tally = Tally::default();
+                    loop {
+                        let batch = match receiver
+                            .lock()
+                            .unwrap_or_else(PoisonError::into_inner)
+                            .as_ref()
+                        {
+                            Some(receiver) => receiver.recv(),
+                            None => break,
+                        };
+                        let Ok(batch) = batch else {
+                            break;
+                        };
+                        for line in batch {
+                            match parse_report(&line) {
+                                Ok(seq) => tally.add(&seq, policy),
+                                Err(_) => tally.malformed += 1,
+                            }
+                        }
+                    }
+                    tally
+                })
+            })
+            .collect();
+
+        // stops early once no worker is left to take batches
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+        for line in reader.lines() {
+            let line = line.unwrap_or_else(|err| panic!("Could not read report: {:?}", err));
+            // blank lines aren't reports
+            if line.trim().is_empty() {
+                continue;
+            }
+            batch.push(line);
+            if batch.len() == BATCH_SIZE {
+                let full = std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE));
+                if sender.send(full).is_err() {
+                    batch.clear();
+                    break;
+                }
+            }
+        }
+        if !batch.is_empty() {
+            let _ = sender.send(batch);
+        }
+        drop(sender);
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .fold(Tally::default(), Tally::merge)
+    })
+}
+
+fn count_safe(input: &str, policy: &SafetyPolicy) -> usize {
+    let lists = parse_input(input);
+
+    lists.iter().filter(|&list| is_safe(list, policy)).count()
+}
+
+fn part_1(input: &str) -> usize {
+    count_safe(input, &SafetyPolicy::PART_1)
+}
+
+fn part_2(input: &str) -> usize {
+    count_safe(input, &SafetyPolicy::PART_2)
+}
+
+fn read_file(path: &str) -> String {
+    std::fs::read_to_string(path).unwrap_or_else(|err| {
         panic!(
-            "File {INPUT_PATH} could not be oppened because of an error: {:?}",
+            "File {path} could not be oppened because of an error: {:?}",
             err
         )
+    })
+}
+
+fn main() {
+    let mut input_path = INPUT_PATH.to_string();
+    // policy flags and config files, applied in order once the mode is known
+    let mut settings: Vec<(String, String)> = vec![];
+    let mut diagnose_reports = false;
+    let mut stream_reports = false;
+    let mut workers = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let flag = arg
+            .strip_prefix("--")
+            .unwrap_or_else(|| panic!("Unexpected argument: {}", arg));
+        match flag {
+            "diagnose" => {
+                diagnose_reports = true;
+                continue;
+            }
+            "stream" => {
+                stream_reports = true;
+                continue;
+            }
+            _ => (),
+        }
+        let value = args
+            .next()
+            .unwrap_or_else(|| panic!("Missing value for --{}", flag));
+        match flag {
+            "input" => input_path = value,
+            "workers" => {
+                workers = value
+                    .parse()
+                    .unwrap_or_else(|err| panic!("Invalid value for workers: {value} ({:?})", err));
+                if workers == 0 {
+                    panic!("Invalid value for workers: 0 (at least one is needed)");
+                }
+            }
+            _ => settings.push((flag.to_string(), value)),
+        }
+    }
+
+    // settings start from what the mode would use without any
+    let base = if stream_reports || diagnose_reports {
+        SafetyPolicy::PART_2
+    } else {
+        SafetyPolicy::PART_1
+    };
+    let policy = (!settings.is_empty()).then(|| {
+        let mut policy = base;
+        for (key, value) in settings.iter() {
+            match key.as_str() {
+                "config" => policy.apply_config(&read_file(value)),
+                _ => policy.set(key, value),
+            }
+        }
+        policy
     });
 
+    if stream_reports {
+        let policy = policy.unwrap_or(base);
+        let tally = if input_path == "-" {
+            classify_stream(std::io::stdin().lock(), &policy, workers)
+        } else {
+            let file = File::open(&input_path).unwrap_or_else(|err| {
+                panic!(
+                    "File {input_path} could not be oppened because of an error: {:?}",
+                    err
+                )
+            });
+            classify_stream(BufReader::new(file), &policy, workers)
+        };
+
+        println!("Safe: {}", tally.safe);
+        println!("Safe with dampener: {}", tally.dampened);
+        println!("Unsafe: {}", tally.rejected);
+        println!("Malformed: {}", tally.malformed);
+        return;
+    }
+
+    let input = read_file(&input_path);
+
+    if diagnose_reports {
+        let policy = policy.unwrap_or(base);
+        for report in parse_input(&input) {
+            println!("{}", render_diagnosis(&report, &diagnose(&report, &policy)));
+        }
+        return;
+    }
+
+    if let Some(policy) = policy {
+        let res = count_safe(&input, &policy);
+        println!("Safe reports: {res}");
+        return;
+    }
+
     let part_1_res = part_1(&input);
     println!("Part 1: {part_1_res}");
 
     let part_2_res = part_2(&input);
     println!("Part 2: {part_2_res}");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIRECTIONS: [Direction; 4] = [
+        Direction::Increasing,
+        Direction::Decreasing,
+        Direction::Either,
+        Direction::NonMonotonic,
+    ];
+
+    fn policy(direction: Direction) -> SafetyPolicy {
+        SafetyPolicy {
+            direction,
+            ..SafetyPolicy::PART_2
+        }
+    }
+
+    // xorshift, so the cases are the same on every run
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self, bound: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % bound
+        }
+    }
+
+    // the smallest removal that makes the report good, found by trying
+    // every subset of levels in order of size
+    fn brute_force(seq: &[i64], policy: &SafetyPolicy) -> Option<usize> {
+        (0..1u32 << seq.len())
+            .filter(|mask| mask.count_ones() as usize <= policy.max_removals)
+            .filter(|mask| {
+                let kept: Vec<i64> = (0..seq.len())
+                    .filter(|i| mask & (1 << i) == 0)
+                    .map(|i| seq[i])
+                    .collect();
+                is_good(&kept, policy)
+            })
+            .map(|mask| mask.count_ones() as usize)
+            .min()
+    }
+
+    #[test]
+    fn short_reports_are_safe() {
+        for direction in DIRECTIONS {
+            let policy = policy(direction);
+            for seq in [&[][..], &[7], &[i64::MIN], &[i64::MAX]] {
+                assert_eq!(first_violation(seq, &policy), None);
+                assert_eq!(dampen(seq, &policy), Some(vec![]));
+            }
+        }
+    }
+
+    #[test]
+    fn two_level_reports_are_judged_on_their_step() {
+        let cases = [
+            ([1, 2], [true, false, true, true]),
+            ([2, 1], [false, true, true, true]),
+            ([1, 4], [true, false, true, true]),
+            ([1, 5], [false, false, false, false]),
+            ([3, 3], [false, false, false, false]),
+        ];
+        for (seq, expected) in cases {
+            for (direction, good) in DIRECTIONS.into_iter().zip(expected) {
+                let policy = policy(direction);
+                assert_eq!(is_good(&seq, &policy), good, "{seq:?} {direction:?}");
+                // dropping either level always leaves a safe report
+                let removed = dampen(&seq, &policy).unwrap();
+                assert_eq!(removed.len(), if good { 0 } else { 1 });
+            }
+        }
+    }
+
+    #[test]
+    fn extreme_levels_do_not_overflow() {
+        let policy = policy(Direction::Either);
+        assert!(!is_good(&[i64::MIN, i64::MAX], &policy));
+        assert!(!is_good(&[i64::MAX, i64::MIN], &policy));
+        assert!(is_good(&[i64::MAX - 3, i64::MAX], &policy));
+        assert!(is_good(&[i64::MIN + 2, i64::MIN], &policy));
+        assert!(is_good(&[i64::MIN, i64::MIN + 1, i64::MIN + 4], &policy));
+        assert_eq!(
+            dampen(&[i64::MIN, i64::MAX, i64::MIN + 1], &policy),
+            Some(vec![1])
+        );
+        assert_eq!(dampen(&[i64::MIN, i64::MAX, 0], &policy), None);
+
+        let wide = SafetyPolicy {
+            max_step: u64::MAX,
+            ..policy
+        };
+        assert!(is_good(&[i64::MIN, i64::MAX], &wide));
+        assert!(is_good(&[i64::MAX, 0, i64::MIN], &wide));
+    }
+
+    #[test]
+    fn dampen_matches_brute_force() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..5000 {
+            let policy = SafetyPolicy {
+                min_step: rng.next(2),
+                max_step: 1 + rng.next(3),
+                direction: DIRECTIONS[rng.next(4) as usize],
+                equal_tolerance: rng.next(3) as usize,
+                max_removals: rng.next(4) as usize,
+            };
+            let len = rng.next(9) as usize;
+            let seq: Vec<i64> = (0..len).map(|_| rng.next(8) as i64).collect();
+
+            let removed = dampen(&seq, &policy);
+            assert_eq!(
+                removed.as_ref().map(Vec::len),
+                brute_force(&seq, &policy),
+                "{seq:?} {policy:?}"
+            );
+            if let Some(removed) = removed {
+                let kept: Vec<i64> = (0..len)
+                    .filter(|i| !removed.contains(i))
+                    .map(|i| seq[i])
+                    .collect();
+                assert!(is_good(&kept, &policy), "{seq:?} {policy:?}");
+            }
+        }
+    }
+}
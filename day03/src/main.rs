@@ -1,4 +1,8 @@
-use regex::Regex;
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::ops::RangeInclusive;
+
+use num_bigint::BigInt;
 
 #[allow(unused)]
 const TEST_INPUT_1: &str =
@@ -10,51 +14,863 @@ const TEST_INPUT_2: &str =
 #[allow(unused)]
 const INPUT_PATH: &str = "input.txt";
 
-fn parse_input<'a, const N: usize>(input: &'a str, re: &'a Regex) -> impl Iterator<Item = &'a str> {
-    re.captures_iter(input)
-        .map(|c| c.extract::<N>())
-        .map(|(matched, _)| matched)
+type Semantics = fn(&mut Interpreter, &[i64]) -> Result<(), Overflow>;
+
+#[derive(Debug)]
+struct InstructionDef {
+    name: &'static str,
+    arity: RangeInclusive<usize>,
+    execute: Semantics,
 }
 
-fn execute_mul(mul: &str) -> u32 {
-    mul[3..]
-        .strip_prefix('(')
-        .unwrap()
-        .strip_suffix(')')
-        .unwrap()
-        .split(',')
-        .map(|ch| ch.parse::<u32>().unwrap())
-        .product()
+#[derive(Debug)]
+struct InstructionSet {
+    defs: Vec<InstructionDef>,
 }
 
-fn part_1(input: &str) -> u32 {
-    let re = Regex::new(r"mul\([0-9]{0,4},[0-9]{0,4}\)").unwrap();
+impl InstructionSet {
+    fn new() -> Self {
+        InstructionSet { defs: vec![] }
+    }
+
+    fn register(
+        mut self,
+        name: &'static str,
+        arity: RangeInclusive<usize>,
+        execute: Semantics,
+    ) -> Self {
+        self.defs.push(InstructionDef {
+            name,
+            arity,
+            execute,
+        });
+        self
+    }
+
+    // the puzzle's instructions
+    fn standard() -> Self {
+        InstructionSet::new()
+            .register("mul", 2..=2, |interpreter, args| {
+                let value = interpreter.product(args)?;
+                interpreter.accumulate(value)
+            })
+            .register("do", 0..=0, |interpreter, _| {
+                interpreter.enabled = true;
+                Ok(())
+            })
+            .register("don't", 0..=0, |interpreter, _| {
+                interpreter.enabled = false;
+                Ok(())
+            })
+    }
 
-    parse_input::<0>(input, &re).map(execute_mul).sum()
+    fn extended() -> Self {
+        InstructionSet::new()
+            .register("mul", 2..=usize::MAX, |interpreter, args| {
+                let value = interpreter.product(args)?;
+                interpreter.accumulate(value)
+            })
+            .register("add", 2..=usize::MAX, |interpreter, args| {
+                let value = interpreter.sum(args)?;
+                interpreter.accumulate(value)
+            })
+            .register("sub", 2..=2, |interpreter, args| {
+                interpreter.accumulate(Number::Small(args[0] as i128 - args[1] as i128))
+            })
+            .register("do", 0..=0, |interpreter, _| {
+                interpreter.enabled = true;
+                Ok(())
+            })
+            .register("don't", 0..=0, |interpreter, _| {
+                interpreter.enabled = false;
+                Ok(())
+            })
+            .register("toggle", 0..=0, |interpreter, _| {
+                interpreter.enabled = !interpreter.enabled;
+                Ok(())
+            })
+            .register("reset", 0..=0, |interpreter, _| {
+                interpreter.total = Number::Small(0);
+                interpreter.enabled_total = Number::Small(0);
+                Ok(())
+            })
+    }
 }
 
-fn part_2(input: &str) -> u32 {
-    let re = Regex::new(r"(mul\([0-9]{0,4},[0-9]{0,4}\))|(do\(\))|(don't\(\))").unwrap();
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct OperandGrammar {
+    min_digits: usize,
+    max_digits: usize,
+    // a single leading `+` or `-`
+    allow_sign: bool,
+    // spaces, tabs and newlines around operands
+    allow_whitespace: bool,
+}
 
-    parse_input::<1>(input, &re)
-        .fold((true, 0), |(can_multiply, sum), op| match &op[0..3] {
-            "do(" => (true, sum),
-            "don" => (false, sum),
-            "mul" => (
-                can_multiply,
-                sum + if can_multiply { execute_mul(op) } else { 0 },
-            ),
-            _ => panic!("Unreachable, op={}", op),
+impl OperandGrammar {
+    // "X and Y are each 1-3 digit numbers"
+    const STRICT: OperandGrammar = OperandGrammar {
+        min_digits: 1,
+        max_digits: 3,
+        allow_sign: false,
+        allow_whitespace: false,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NearMissKind {
+    MissingOperand,
+    TooFewDigits(usize),
+    TooManyDigits(usize),
+    Overflow,
+    UnexpectedSign,
+    UnexpectedWhitespace,
+    UnexpectedByte(u8),
+    WrongArity(usize),
+}
+
+impl std::fmt::Display for NearMissKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NearMissKind::MissingOperand => write!(f, "missing operand"),
+            NearMissKind::TooFewDigits(n) => write!(f, "operand has too few digits ({n})"),
+            NearMissKind::TooManyDigits(n) => write!(f, "operand has too many digits ({n})"),
+            NearMissKind::Overflow => write!(f, "operand does not fit in 64 bits"),
+            NearMissKind::UnexpectedSign => write!(f, "signed operand"),
+            NearMissKind::UnexpectedWhitespace => write!(f, "whitespace around operand"),
+            NearMissKind::UnexpectedByte(byte) => write!(f, "unexpected '{}'", byte.escape_ascii()),
+            NearMissKind::WrongArity(n) => write!(f, "wrong number of operands ({n})"),
+        }
+    }
+}
+
+// an instruction name and `(` followed by something the grammar rejects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct NearMiss {
+    offset: usize,
+    len: usize,
+    kind: NearMissKind,
+}
+
+#[derive(Debug)]
+struct Instruction<'s> {
+    def: &'s InstructionDef,
+    args: Vec<i64>,
+}
+
+#[derive(Debug)]
+struct Token<'s> {
+    offset: usize,
+    len: usize,
+    instruction: Instruction<'s>,
+}
+
+struct Lexer<'a, 's> {
+    memory: &'a [u8],
+    pos: usize,
+    set: &'s InstructionSet,
+    grammar: OperandGrammar,
+    lenient: bool,
+    // offset of `memory` within the whole input
+    base: usize,
+    // more input may follow `memory`
+    partial: bool,
+    // the current match attempt looked past the end of `memory`
+    hit_end: bool,
+}
+
+impl<'a, 's> Lexer<'a, 's> {
+    fn new(memory: &'a [u8], set: &'s InstructionSet, grammar: OperandGrammar) -> Self {
+        Lexer {
+            memory,
+            pos: 0,
+            set,
+            grammar,
+            lenient: false,
+            base: 0,
+            partial: false,
+            hit_end: false,
+        }
+    }
+
+    // also yield near misses instead of treating them as corrupted memory
+    fn lenient(mut self) -> Self {
+        self.lenient = true;
+        self
+    }
+
+    // lex a chunk that starts at `base`; unless it is the last one, the
+    // lexer stops before any instruction that could continue in the next chunk
+    fn chunk(mut self, base: usize, is_last: bool) -> Self {
+        self.base = base;
+        self.partial = !is_last;
+        self
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        let byte = self.memory.get(self.pos).copied();
+        self.hit_end |= byte.is_none();
+        byte
+    }
+
+    fn eat(&mut self, literal: &str) -> Option<()> {
+        let rest = &self.memory[self.pos..];
+        if rest.starts_with(literal.as_bytes()) {
+            self.pos += literal.len();
+            return Some(());
+        }
+        self.hit_end |= literal.as_bytes().starts_with(rest);
+        None
+    }
+
+    fn whitespace(&mut self) -> Result<(), NearMissKind> {
+        let start = self.pos;
+        while self.peek().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+        if self.pos > start && !self.grammar.allow_whitespace {
+            return Err(NearMissKind::UnexpectedWhitespace);
+        }
+        Ok(())
+    }
+
+    fn number(&mut self) -> Result<i64, NearMissKind> {
+        self.whitespace()?;
+
+        let negative = match self.peek() {
+            Some(sign @ (b'+' | b'-')) => {
+                self.pos += 1;
+                if !self.grammar.allow_sign {
+                    return Err(NearMissKind::UnexpectedSign);
+                }
+                sign == b'-'
+            }
+            _ => false,
+        };
+
+        let digits = self.memory[self.pos..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+        let start = self.pos;
+        self.pos += digits;
+        self.hit_end |= self.pos == self.memory.len();
+        if digits == 0 {
+            return Err(NearMissKind::MissingOperand);
+        }
+        if digits < self.grammar.min_digits {
+            return Err(NearMissKind::TooFewDigits(digits));
+        }
+        if digits > self.grammar.max_digits {
+            return Err(NearMissKind::TooManyDigits(digits));
+        }
+
+        let number = self.memory[start..self.pos]
+            .iter()
+            .try_fold(0i64, |acc, digit| {
+                acc.checked_mul(10)?.checked_add((digit - b'0') as i64)
+            })
+            .ok_or(NearMissKind::Overflow)?;
+
+        self.whitespace()?;
+        Ok(if negative { -number } else { number })
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), NearMissKind> {
+        match self.peek() {
+            Some(next) if next == byte => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(next) => {
+                self.pos += 1;
+                Err(NearMissKind::UnexpectedByte(next))
+            }
+            None => Err(NearMissKind::MissingOperand),
+        }
+    }
+
+    // called right after the opening parenthesis
+    fn args(&mut self) -> Result<Vec<i64>, NearMissKind> {
+        let mut args = vec![];
+        self.whitespace()?;
+        if self.eat(")").is_some() {
+            return Ok(args);
+        }
+
+        loop {
+            args.push(self.number()?);
+            if self.eat(")").is_some() {
+                return Ok(args);
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    fn instruction(&mut self) -> Result<Instruction<'s>, Option<NearMiss>> {
+        let start = self.pos;
+        let mut near_miss: Option<NearMiss> = None;
+
+        for def in self.set.defs.iter() {
+            self.pos = start;
+            if self.eat(def.name).is_none() || self.eat("(").is_none() {
+                continue;
+            }
+
+            let args = self.args().and_then(|args| {
+                if def.arity.contains(&args.len()) {
+                    Ok(args)
+                } else {
+                    Err(NearMissKind::WrongArity(args.len()))
+                }
+            });
+            match args {
+                Ok(args) => return Ok(Instruction { def, args }),
+                Err(kind) => {
+                    let len = self.pos - start;
+                    if near_miss.is_none_or(|near_miss| near_miss.len < len) {
+                        near_miss = Some(NearMiss {
+                            offset: self.base + start,
+                            len,
+                            kind,
+                        });
+                    }
+                }
+            }
+        }
+
+        Err(near_miss)
+    }
+}
+
+impl<'s> Iterator for Lexer<'_, 's> {
+    type Item = Result<Token<'s>, NearMiss>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.memory.len() {
+            let offset = self.pos;
+            self.hit_end = false;
+            let instruction = self.instruction();
+            if self.partial && self.hit_end {
+                // wait for the next chunk to decide
+                self.pos = offset;
+                return None;
+            }
+
+            match instruction {
+                Ok(instruction) => {
+                    return Some(Ok(Token {
+                        offset: self.base + offset,
+                        len: self.pos - offset,
+                        instruction,
+                    }))
+                }
+                Err(near_miss) => {
+                    // everything that is not a whole instruction is corrupted memory
+                    self.pos = offset + 1;
+                    if let Some(near_miss) = near_miss.filter(|_| self.lenient) {
+                        return Some(Err(near_miss));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Overflow;
+
+// Results stay in an i128 while they fit. Past that, checked arithmetic
+// reports an overflow and unbounded arithmetic switches to a BigInt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Number {
+    Small(i128),
+    Big(BigInt),
+}
+
+impl Number {
+    fn to_big(&self) -> BigInt {
+        match self {
+            Number::Small(n) => BigInt::from(*n),
+            Number::Big(n) => n.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Number::Small(n) => write!(f, "{n}"),
+            Number::Big(n) => write!(f, "{n}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arithmetic {
+    Checked,
+    Unbounded,
+}
+
+impl Arithmetic {
+    fn add(self, left: &Number, right: &Number) -> Result<Number, Overflow> {
+        if let (Number::Small(left), Number::Small(right)) = (left, right) {
+            if let Some(sum) = left.checked_add(*right) {
+                return Ok(Number::Small(sum));
+            }
+        }
+        match self {
+            Arithmetic::Checked => Err(Overflow),
+            Arithmetic::Unbounded => Ok(Number::Big(left.to_big() + right.to_big())),
+        }
+    }
+
+    fn mul(self, left: &Number, right: &Number) -> Result<Number, Overflow> {
+        if let (Number::Small(left), Number::Small(right)) = (left, right) {
+            if let Some(product) = left.checked_mul(*right) {
+                return Ok(Number::Small(product));
+            }
+        }
+        match self {
+            Arithmetic::Checked => Err(Overflow),
+            Arithmetic::Unbounded => Ok(Number::Big(left.to_big() * right.to_big())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Interpreter {
+    enabled: bool,
+    arithmetic: Arithmetic,
+    // sum of every result, as if `do()`/`don't()` did not exist
+    total: Number,
+    // sum of the results computed while enabled
+    enabled_total: Number,
+    // result of the instruction that is currently executing, if any
+    value: Option<Number>,
+}
+
+impl Interpreter {
+    fn new(arithmetic: Arithmetic) -> Self {
+        Interpreter {
+            enabled: true,
+            arithmetic,
+            total: Number::Small(0),
+            enabled_total: Number::Small(0),
+            value: None,
+        }
+    }
+
+    fn product(&self, args: &[i64]) -> Result<Number, Overflow> {
+        args.iter().try_fold(Number::Small(1), |acc, &arg| {
+            self.arithmetic.mul(&acc, &Number::Small(arg as i128))
         })
-        .1
+    }
+
+    fn sum(&self, args: &[i64]) -> Result<Number, Overflow> {
+        args.iter().try_fold(Number::Small(0), |acc, &arg| {
+            self.arithmetic.add(&acc, &Number::Small(arg as i128))
+        })
+    }
+
+    fn accumulate(&mut self, value: Number) -> Result<(), Overflow> {
+        let total = self.arithmetic.add(&self.total, &value)?;
+        if self.enabled {
+            self.enabled_total = self.arithmetic.add(&self.enabled_total, &value)?;
+        }
+        self.total = total;
+        self.value = Some(value);
+        Ok(())
+    }
+
+    fn execute(&mut self, instruction: &Instruction) -> Result<Option<Number>, Overflow> {
+        self.value = None;
+        (instruction.def.execute)(self, &instruction.args)?;
+        Ok(self.value.take())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TraceEntry {
+    offset: usize,
+    len: usize,
+    // state right before the instruction ran
+    was_enabled: bool,
+    is_enabled: bool,
+    value: Option<Number>,
+}
+
+impl TraceEntry {
+    fn contribution(&self) -> Option<&Number> {
+        self.value.as_ref().filter(|_| self.was_enabled)
+    }
+}
+
+#[derive(Debug)]
+enum RunError {
+    Io(std::io::Error),
+    Overflow { offset: usize },
+    // streaming can't tell yet whether this is an instruction
+    TooLong { offset: usize, limit: usize },
+}
+
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::Io(err) => write!(f, "could not read memory: {err}"),
+            RunError::Overflow { offset } => {
+                write!(f, "arithmetic overflow in instruction at byte {offset}")
+            }
+            RunError::TooLong { offset, limit } => write!(
+                f,
+                "possible instruction at byte {offset} is longer than {limit} bytes, which can't be streamed"
+            ),
+        }
+    }
+}
+
+impl From<std::io::Error> for RunError {
+    fn from(err: std::io::Error) -> Self {
+        RunError::Io(err)
+    }
+}
+
+#[derive(Debug)]
+struct Execution {
+    result: Interpreter,
+    near_misses: Vec<NearMiss>,
+    trace: Vec<TraceEntry>,
+}
+
+impl Execution {
+    fn new(arithmetic: Arithmetic) -> Self {
+        Execution {
+            result: Interpreter::new(arithmetic),
+            near_misses: vec![],
+            trace: vec![],
+        }
+    }
+
+    fn feed(&mut self, lexer: &mut Lexer, trace: bool) -> Result<(), RunError> {
+        for token in lexer {
+            let token = match token {
+                Ok(token) => token,
+                Err(near_miss) => {
+                    self.near_misses.push(near_miss);
+                    continue;
+                }
+            };
+
+            let was_enabled = self.result.enabled;
+            let value = self
+                .result
+                .execute(&token.instruction)
+                .map_err(|Overflow| RunError::Overflow {
+                    offset: token.offset,
+                })?;
+            if trace {
+                self.trace.push(TraceEntry {
+                    offset: token.offset,
+                    len: token.len,
+                    was_enabled,
+                    is_enabled: self.result.enabled,
+                    value,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn run(mut lexer: Lexer, trace: bool, arithmetic: Arithmetic) -> Result<Execution, RunError> {
+    let mut execution = Execution::new(arithmetic);
+    execution.feed(&mut lexer, trace)?;
+    Ok(execution)
+}
+
+// Only the unfinished tail of the previous chunk is kept between reads, so
+// memory stays bounded by the chunk size plus `MAX_PENDING`.
+fn run_stream<R: Read>(
+    mut reader: R,
+    chunk_size: usize,
+    set: &InstructionSet,
+    grammar: OperandGrammar,
+    lenient: bool,
+    arithmetic: Arithmetic,
+) -> Result<Execution, RunError> {
+    // a candidate can only get this long with whitespace or unbounded arity;
+    // deciding it needs the whole of it in memory, so streaming gives up
+    const MAX_PENDING: usize = 1024;
+
+    let mut execution = Execution::new(arithmetic);
+    let mut buffer = Vec::with_capacity(chunk_size + MAX_PENDING);
+    let mut chunk = vec![0; chunk_size];
+    let mut base = 0;
+
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(read) => read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        let is_last = read == 0;
+        buffer.extend_from_slice(&chunk[..read]);
+
+        let lexer = Lexer::new(&buffer, set, grammar).chunk(base, is_last);
+        let mut lexer = if lenient { lexer.lenient() } else { lexer };
+        execution.feed(&mut lexer, false)?;
+
+        let consumed = lexer.pos;
+        if buffer.len() - consumed > MAX_PENDING {
+            return Err(RunError::TooLong {
+                offset: base + consumed,
+                limit: MAX_PENDING,
+            });
+        }
+        buffer.drain(..consumed);
+        base += consumed;
+
+        if is_last {
+            return Ok(execution);
+        }
+    }
+}
+
+fn print_trace(input: &str, trace: &[TraceEntry]) {
+    println!(
+        "{:>8}  {:<24} {:<8} {:>12}",
+        "offset", "instruction", "state", "contribution"
+    );
+    for entry in trace {
+        println!(
+            "{:>8}  {:<24} {:<8} {:>12}",
+            entry.offset,
+            &input[entry.offset..entry.offset + entry.len],
+            if entry.was_enabled {
+                "enabled"
+            } else {
+                "disabled"
+            },
+            entry
+                .contribution()
+                .map_or("0".to_string(), |value| value.to_string())
+        );
+    }
+}
+
+fn render(input: &str, trace: &[TraceEntry]) -> String {
+    const GREEN: &str = "\x1b[1;32m";
+    const GRAY: &str = "\x1b[90m";
+    const CYAN: &str = "\x1b[1;36m";
+    const MAGENTA: &str = "\x1b[1;35m";
+    const YELLOW: &str = "\x1b[1;33m";
+    const RESET: &str = "\x1b[0m";
+
+    let mut rendered = String::with_capacity(input.len());
+    let mut pos = 0;
+    for entry in trace {
+        let color = match &entry.value {
+            Some(_) if entry.was_enabled => GREEN,
+            Some(_) => GRAY,
+            // control instructions are colored by the state they leave behind
+            None if entry.was_enabled == entry.is_enabled => YELLOW,
+            None if entry.is_enabled => CYAN,
+            None => MAGENTA,
+        };
+        let end = entry.offset + entry.len;
+        rendered.push_str(&input[pos..entry.offset]);
+        rendered.push_str(color);
+        rendered.push_str(&input[entry.offset..end]);
+        rendered.push_str(RESET);
+        pos = end;
+    }
+    rendered.push_str(&input[pos..]);
+
+    rendered
 }
 
 fn main() {
-    let input = &std::fs::read_to_string(INPUT_PATH).unwrap();
+    let mut input_path = INPUT_PATH.to_string();
+    let mut set = InstructionSet::standard();
+    let mut grammar = OperandGrammar::STRICT;
+    let mut lenient = false;
+    let mut show_trace = false;
+    let mut show_render = false;
+    let mut chunk_size = None;
+    let mut arithmetic = Arithmetic::Checked;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input_path = args.next().expect("Missing value for --input"),
+            "--extended" => set = InstructionSet::extended(),
+            "--min-digits" | "--max-digits" => {
+                let value = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or_else(|| panic!("Missing or invalid value for {}", arg));
+                if arg == "--min-digits" {
+                    grammar.min_digits = value;
+                } else {
+                    grammar.max_digits = value;
+                }
+            }
+            "--allow-sign" => grammar.allow_sign = true,
+            "--allow-whitespace" => grammar.allow_whitespace = true,
+            "--lenient" => lenient = true,
+            "--unbounded" => arithmetic = Arithmetic::Unbounded,
+            "--trace" => show_trace = true,
+            "--render" => show_render = true,
+            "--chunk-size" => {
+                let size = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .filter(|&size| size > 0)
+                    .unwrap_or_else(|| panic!("Missing or invalid value for --chunk-size"));
+                chunk_size = Some(size);
+            }
+            _ => panic!("Unexpected argument: {}", arg),
+        }
+    }
+
+    if let Some(chunk_size) = chunk_size {
+        if show_trace || show_render {
+            panic!("--trace and --render need the whole input and can't be used with --chunk-size");
+        }
+
+        let file = File::open(&input_path).unwrap();
+        let execution = run_stream(file, chunk_size, &set, grammar, lenient, arithmetic)
+            .unwrap_or_else(|err| panic!("{err}"));
+        for near_miss in execution.near_misses.iter() {
+            println!("Near miss at byte {}: {}", near_miss.offset, near_miss.kind);
+        }
+        println!("Part 1: {}", execution.result.total);
+        println!("Part 2: {}", execution.result.enabled_total);
+        return;
+    }
+
+    let input = &std::fs::read_to_string(&input_path).unwrap();
+
+    let lexer = Lexer::new(input.as_bytes(), &set, grammar);
+    let lexer = if lenient { lexer.lenient() } else { lexer };
+    let execution =
+        run(lexer, show_trace || show_render, arithmetic).unwrap_or_else(|err| panic!("{err}"));
+
+    if show_trace {
+        print_trace(input, &execution.trace);
+    }
+    if show_render {
+        println!("{}", render(input, &execution.trace));
+    }
+    for near_miss in execution.near_misses.iter() {
+        println!(
+            "Near miss at byte {}: {:?} ({})",
+            near_miss.offset,
+            String::from_utf8_lossy(
+                &input.as_bytes()[near_miss.offset..near_miss.offset + near_miss.len]
+            ),
+            near_miss.kind
+        );
+    }
+    println!("Part 1: {}", execution.result.total);
+    println!("Part 2: {}", execution.result.enabled_total);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUTS: [&str; 7] = [
+        TEST_INPUT_1,
+        TEST_INPUT_2,
+        "don't()mul(2,3)do()mul(4,5)don't(mul(6,7)",
+        "mumul(2,3)domul(1,1)do(don't()mul(8,9)do()",
+        "mul(1234,5)mul(1, 2)mul(+3,-4)mul(7,8",
+        "add(1,2,3)sub(5,2)toggle()mul(2,2,2)reset()mul(3,3)",
+        "mul(\n 2 ,\t3 )don't( )mul( 4,5)do( )mul(6 ,7)",
+    ];
+
+    fn grammars() -> [OperandGrammar; 2] {
+        [
+            OperandGrammar::STRICT,
+            OperandGrammar {
+                allow_sign: true,
+                allow_whitespace: true,
+                ..OperandGrammar::STRICT
+            },
+        ]
+    }
+
+    fn whole(
+        input: &str,
+        set: &InstructionSet,
+        grammar: OperandGrammar,
+        lenient: bool,
+    ) -> Execution {
+        let lexer = Lexer::new(input.as_bytes(), set, grammar);
+        let lexer = if lenient { lexer.lenient() } else { lexer };
+        run(lexer, false, Arithmetic::Checked).unwrap()
+    }
+
+    #[test]
+    fn examples() {
+        let set = InstructionSet::standard();
+        let execution = whole(TEST_INPUT_1, &set, OperandGrammar::STRICT, false);
+        assert_eq!(execution.result.total, Number::Small(161));
+        let execution = whole(TEST_INPUT_2, &set, OperandGrammar::STRICT, false);
+        assert_eq!(execution.result.enabled_total, Number::Small(48));
+    }
+
+    #[test]
+    fn streaming_matches_whole_input() {
+        for set in [InstructionSet::standard(), InstructionSet::extended()] {
+            for grammar in grammars() {
+                for lenient in [false, true] {
+                    for input in INPUTS {
+                        let expected = whole(input, &set, grammar, lenient);
+                        for chunk_size in 1..=input.len() + 1 {
+                            let streamed = run_stream(
+                                input.as_bytes(),
+                                chunk_size,
+                                &set,
+                                grammar,
+                                lenient,
+                                Arithmetic::Checked,
+                            )
+                            .unwrap();
+                            let context = format!("{input:?} in chunks of {chunk_size}");
+                            assert_eq!(streamed.result, expected.result, "{context}");
+                            assert_eq!(streamed.near_misses, expected.near_misses, "{context}");
+                        }
+                    }
+                }
+            }
+        }
+    }
 
-    let part_1_res = part_1(input);
-    println!("Part 1: {part_1_res}");
+    #[test]
+    fn long_candidates_are_not_dropped() {
+        let input = format!("mul({}2,3)", " ".repeat(2000));
+        let set = InstructionSet::standard();
+        let grammar = grammars()[1];
+        let expected = whole(&input, &set, grammar, false);
+        assert_eq!(expected.result.total, Number::Small(6));
 
-    let part_2_res = part_2(input);
-    println!("Part 2: {part_2_res}");
+        let streamed = |chunk_size| {
+            run_stream(
+                input.as_bytes(),
+                chunk_size,
+                &set,
+                grammar,
+                false,
+                Arithmetic::Checked,
+            )
+        };
+        assert_eq!(streamed(4096).unwrap().result, expected.result);
+        assert!(matches!(
+            streamed(100),
+            Err(RunError::TooLong { offset: 0, .. })
+        ));
+    }
 }
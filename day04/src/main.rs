@@ -1,3 +1,7 @@
+use std::collections::{HashMap, VecDeque};
+
+use unicode_segmentation::UnicodeSegmentation;
+
 #[allow(unused)]
 const TEST_INPUT_1: &str = "MMMSXXMASM
                             MSAMXMSMSA
@@ -31,185 +35,921 @@ const TEST_INPUT_SIMPLER: &str = "1234h
 #[allow(unused)]
 const INPUT_PATH: &str = "input.txt";
 
-#[derive(PartialEq)]
-enum Diagonal {
-    Ascending,
-    Descending,
-}
-
-enum SplitKind {
-    Rows,
-    Columns,
-    Diagonal(Diagonal, usize),
-}
-
-fn get_diagonals(board: &[Vec<char>], diag: Diagonal, size: usize) -> Vec<Vec<char>> {
-    let mut diagonals = vec![];
-    for (i, row) in board.iter().enumerate() {
-        for (j, _) in row.iter().enumerate() {
-            let mut curr_diagonal = vec![];
-            let mut did_break = false;
-            for next in 0..size {
-                match diag {
-                    Diagonal::Descending => {
-                        if i + next < board.len() && j + next < board[0].len() {
-                            let curr_point = board[i + next][j + next];
-                            curr_diagonal.push(curr_point);
-                        } else {
-                            did_break = true;
-                            break;
-                        }
-                    }
-                    Diagonal::Ascending => {
-                        if i + next < board.len() && j as i32 - next as i32 >= 0 {
-                            let curr_point = board[i + next][j - next];
-                            curr_diagonal.push(curr_point);
-                        } else {
-                            did_break = true;
-                            break;
-                        }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    fn opposite(self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::NorthEast => Direction::SouthWest,
+            Direction::East => Direction::West,
+            Direction::SouthEast => Direction::NorthWest,
+            Direction::South => Direction::North,
+            Direction::SouthWest => Direction::NorthEast,
+            Direction::West => Direction::East,
+            Direction::NorthWest => Direction::SouthEast,
+        }
+    }
+
+    // (row, column) step
+    fn delta(self) -> (isize, isize) {
+        match self {
+            Direction::North => (-1, 0),
+            Direction::NorthEast => (-1, 1),
+            Direction::East => (0, 1),
+            Direction::SouthEast => (1, 1),
+            Direction::South => (1, 0),
+            Direction::SouthWest => (1, -1),
+            Direction::West => (0, -1),
+            Direction::NorthWest => (-1, -1),
+        }
+    }
+}
+
+// a grapheme cluster, numbered in order of first appearance in its grid
+type Letter = u32;
+
+// what to do with rows that aren't as wide as the others
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ragged {
+    // every row must be as wide as the first one
+    Reject,
+    // short rows are filled up to the widest one with empty cells
+    Pad,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RaggedRow {
+    row: usize,
+    width: usize,
+    expected: usize,
+}
+
+impl std::fmt::Display for RaggedRow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Row {} has {} cells, expected {} (use --pad to fill short rows)",
+            self.row + 1,
+            self.width,
+            self.expected
+        )
+    }
+}
+
+// row-major storage; all views below borrow from it instead of copying
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Grid {
+    cells: Vec<Letter>,
+    // text of each letter, padding being the empty string
+    letters: Vec<String>,
+    width: usize,
+    height: usize,
+}
+
+impl Grid {
+    fn from_rows(rows: Vec<Vec<&str>>, ragged: Ragged) -> Result<Self, RaggedRow> {
+        let width = match ragged {
+            Ragged::Reject => rows.first().map_or(0, Vec::len),
+            Ragged::Pad => rows.iter().map(Vec::len).max().unwrap_or(0),
+        };
+        if ragged == Ragged::Reject {
+            if let Some(row) = rows.iter().position(|row| row.len() != width) {
+                return Err(RaggedRow {
+                    row,
+                    width: rows[row].len(),
+                    expected: width,
+                });
+            }
+        }
+
+        let mut ids: HashMap<&str, Letter> = HashMap::new();
+        let mut letters = vec![];
+        let mut cells = Vec::with_capacity(rows.len() * width);
+        for row in rows.iter() {
+            let padding = std::iter::repeat_n("", width - row.len());
+            for text in row.iter().copied().chain(padding) {
+                let letter = *ids.entry(text).or_insert_with(|| {
+                    letters.push(text.to_string());
+                    (letters.len() - 1) as Letter
+                });
+                cells.push(letter);
+            }
+        }
+
+        Ok(Grid {
+            height: rows.len(),
+            cells,
+            letters,
+            width,
+        })
+    }
+
+    fn letter(&self, text: &str) -> Option<Letter> {
+        let letter = self.letters.iter().position(|letter| letter == text)?;
+        Some(letter as Letter)
+    }
+
+    fn text(&self, letter: Letter) -> &str {
+        &self.letters[letter as usize]
+    }
+
+    fn positions(&self) -> impl Iterator<Item = (usize, usize)> {
+        let width = self.width;
+        (0..self.height).flat_map(move |row| (0..width).map(move |column| (row, column)))
+    }
+
+    // `steps` cells away from `from` in `direction`, if that's still on the grid
+    fn step(
+        &self,
+        from: (usize, usize),
+        direction: Direction,
+        steps: usize,
+    ) -> Option<(usize, usize)> {
+        let (dy, dx) = direction.delta();
+        let row = from.0.checked_add_signed(dy * steps as isize)?;
+        let column = from.1.checked_add_signed(dx * steps as isize)?;
+        (row < self.height && column < self.width).then_some((row, column))
+    }
+
+    fn line(&self, start: (usize, usize), direction: Direction, len: usize) -> Option<Line<'_>> {
+        if len > 0 {
+            self.step(start, direction, len - 1)?;
+        }
+        Some(Line {
+            grid: self,
+            start,
+            direction,
+            len,
+        })
+    }
+
+    // every maximal line running in `direction`, so each cell is on exactly one
+    fn lines(&self, direction: Direction) -> impl Iterator<Item = Line<'_>> {
+        let (dy, dx) = direction.delta();
+        let room = |at: usize, delta: isize, size: usize| match delta {
+            1 => size - at,
+            -1 => at + 1,
+            _ => usize::MAX,
+        };
+
+        self.positions()
+            .filter(move |&start| self.step(start, direction.opposite(), 1).is_none())
+            .map(move |start| Line {
+                grid: self,
+                start,
+                direction,
+                len: room(start.0, dy, self.height).min(room(start.1, dx, self.width)),
+            })
+    }
+
+    fn window(&self, start: (usize, usize), width: usize, height: usize) -> Option<Window<'_>> {
+        (start.0 + height <= self.height && start.1 + width <= self.width).then_some(Window {
+            grid: self,
+            start,
+            width,
+            height,
+        })
+    }
+
+    fn windows(&self, width: usize, height: usize) -> impl Iterator<Item = Window<'_>> {
+        self.positions()
+            .filter_map(move |start| self.window(start, width, height))
+    }
+}
+
+// a straight run of cells: part of a row, a column or a diagonal
+#[derive(Debug, Clone, Copy)]
+struct Line<'g> {
+    grid: &'g Grid,
+    start: (usize, usize),
+    direction: Direction,
+    len: usize,
+}
+
+impl<'g> Line<'g> {
+    fn position(&self, i: usize) -> (usize, usize) {
+        self.grid.step(self.start, self.direction, i).unwrap()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = Letter> + 'g {
+        let (dy, dx) = self.direction.delta();
+        let stride = dy * self.grid.width as isize + dx;
+        let first = (self.start.0 * self.grid.width + self.start.1) as isize;
+        let cells = &self.grid.cells;
+        (0..self.len as isize).map(move |i| cells[(first + i * stride) as usize])
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Window<'g> {
+    grid: &'g Grid,
+    start: (usize, usize),
+    width: usize,
+    height: usize,
+}
+
+impl<'g> Window<'g> {
+    fn rows(&self) -> impl Iterator<Item = &'g [Letter]> {
+        let Window {
+            grid,
+            start,
+            width,
+            height,
+        } = *self;
+        (start.0..start.0 + height).map(move |row| {
+            let offset = row * grid.width + start.1;
+            &grid.cells[offset..offset + width]
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct WordMatch {
+    // index into the searched words
+    word: usize,
+    // (row, column) of the first letter
+    start: (usize, usize),
+    direction: Direction,
+}
+
+impl WordMatch {
+    fn cells<'g>(&self, grid: &'g Grid, len: usize) -> impl Iterator<Item = (usize, usize)> + 'g {
+        let line = grid.line(self.start, self.direction, len).unwrap();
+        (0..line.len).map(move |i| line.position(i))
+    }
+}
+
+// Aho-Corasick automaton over the letters of all words, with the failure
+// links folded into a dense transition table
+struct Dictionary {
+    // length of each word in letters
+    lengths: Vec<usize>,
+    // words that read the same backwards
+    palindromes: Vec<bool>,
+    // sorted letters that appear in any word; everything else resets the search
+    alphabet: Vec<String>,
+    // `transitions[state * alphabet.len() + letter]`
+    transitions: Vec<usize>,
+    // words ending in each state, including the ones reached through failure links
+    outputs: Vec<Vec<usize>>,
+}
+
+impl Dictionary {
+    fn new(words: &[&str]) -> Self {
+        let words: Vec<Vec<&str>> = words
+            .iter()
+            .map(|word| word.graphemes(true).collect())
+            .collect();
+        let mut alphabet: Vec<&str> = words.iter().flatten().copied().collect();
+        alphabet.sort_unstable();
+        alphabet.dedup();
+        let letters = alphabet.len();
+
+        // trie first, `None` meaning no edge yet
+        let mut trie: Vec<Vec<Option<usize>>> = vec![vec![None; letters]];
+        let mut outputs: Vec<Vec<usize>> = vec![vec![]];
+        for (word_idx, word) in words.iter().enumerate() {
+            if word.is_empty() {
+                continue;
+            }
+            let mut state = 0;
+            for letter in word {
+                let letter = alphabet.binary_search(letter).unwrap();
+                state = match trie[state][letter] {
+                    Some(next) => next,
+                    None => {
+                        trie.push(vec![None; letters]);
+                        outputs.push(vec![]);
+                        trie[state][letter] = Some(trie.len() - 1);
+                        trie.len() - 1
                     }
                 };
             }
+            outputs[state].push(word_idx);
+        }
 
-            if let Some(diagonal) = if did_break { None } else { Some(curr_diagonal) } {
-                diagonals.push(diagonal);
+        // breadth first, so the failure state is complete before it is used
+        let mut transitions = vec![0; trie.len() * letters];
+        let mut fail = vec![0; trie.len()];
+        let mut queue = VecDeque::from([0]);
+        while let Some(state) = queue.pop_front() {
+            for letter in 0..letters {
+                let next = match trie[state][letter] {
+                    Some(next) => next,
+                    None => {
+                        transitions[state * letters + letter] =
+                            transitions[fail[state] * letters + letter];
+                        continue;
+                    }
+                };
+                if state != 0 {
+                    fail[next] = transitions[fail[state] * letters + letter];
+                    let inherited = outputs[fail[next]].clone();
+                    outputs[next].extend(inherited);
+                }
+                transitions[state * letters + letter] = next;
+                queue.push_back(next);
             }
         }
+
+        Dictionary {
+            lengths: words.iter().map(Vec::len).collect(),
+            palindromes: words
+                .iter()
+                .map(|word| word.iter().eq(word.iter().rev()))
+                .collect(),
+            alphabet: alphabet.into_iter().map(str::to_string).collect(),
+            transitions,
+            outputs,
+        }
     }
 
-    diagonals
-}
+    // `letter` is an index into the alphabet
+    fn next(&self, state: usize, letter: Option<usize>) -> usize {
+        match letter {
+            Some(letter) => self.transitions[state * self.alphabet.len() + letter],
+            None => 0,
+        }
+    }
 
-fn split_into(board: &[Vec<char>], split_kind: SplitKind) -> Vec<Vec<char>> {
-    match split_kind {
-        SplitKind::Rows => board.to_vec(),
-        SplitKind::Columns => {
-            let mut columns = vec![vec![]; board[0].len()];
-            for row in board.iter() {
-                for (j, &cell) in row.iter().enumerate() {
-                    columns[j].push(cell);
+    // one pass over every line of the grid in each of the eight directions
+    fn search(&self, grid: &Grid) -> Vec<WordMatch> {
+        let mut matches = vec![];
+        // the grid's letters in terms of the alphabet, looked up once per search
+        let translated: Vec<Option<usize>> = grid
+            .letters
+            .iter()
+            .map(|text| self.alphabet.binary_search(text).ok())
+            .collect();
+
+        for direction in Direction::ALL {
+            for line in grid.lines(direction) {
+                let mut state = 0;
+                for (i, letter) in line.iter().enumerate() {
+                    state = self.next(state, translated[letter as usize]);
+                    for &word in self.outputs[state].iter() {
+                        let len = self.lengths[word];
+                        // a single letter reads the same in every direction
+                        if len == 1 && direction != Direction::East {
+                            continue;
+                        }
+                        // and a palindrome the same both ways along a line,
+                        // so only the southward and eastward readings count
+                        if self.palindromes[word] && direction.delta() < (0, 0) {
+                            continue;
+                        }
+                        matches.push(WordMatch {
+                            word,
+                            start: line.position(i + 1 - len),
+                            direction,
+                        });
+                    }
                 }
             }
-            columns
         }
-        SplitKind::Diagonal(diagonal, size) => get_diagonals(board, diagonal, size),
+
+        matches.sort_by_key(|found| (found.start, found.word, found.direction));
+        matches
     }
 }
 
-fn get_blocks(board: &[Vec<char>], width: usize, height: usize) -> Vec<Vec<Vec<char>>> {
-    let mut blocks = vec![];
-    for (i, row) in board.iter().enumerate() {
-        for (j, _) in row.iter().enumerate() {
-            let mut curr_block = vec![vec![]; width];
-            let mut did_break = false;
-            for y in 0..height {
-                for x in 0..width {
-                    if i + y < board.len() && j + x < board[0].len() {
-                        curr_block[y].push(board[i + y][j + x]);
-                    } else {
-                        did_break = true;
-                        break;
-                    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct WordReport {
+    word: usize,
+    matches: Vec<WordMatch>,
+    // matches sharing at least one cell with another match
+    overlapping: usize,
+}
+
+fn report(grid: &Grid, words: &[&str], matches: &[WordMatch]) -> Vec<WordReport> {
+    let cells = |found: &WordMatch| found.cells(grid, words[found.word].graphemes(true).count());
+
+    let mut usage = vec![0usize; grid.cells.len()];
+    for found in matches {
+        for (row, column) in cells(found) {
+            usage[row * grid.width + column] += 1;
+        }
+    }
+
+    let mut reports: Vec<WordReport> = (0..words.len())
+        .map(|word| WordReport {
+            word,
+            matches: vec![],
+            overlapping: 0,
+        })
+        .collect();
+    for found in matches {
+        let report = &mut reports[found.word];
+        report.matches.push(*found);
+        if cells(found).any(|(row, column)| usage[row * grid.width + column] > 1) {
+            report.overlapping += 1;
+        }
+    }
+
+    reports
+}
+
+struct WordSearch<'a> {
+    grid: &'a Grid,
+}
+
+impl<'a> WordSearch<'a> {
+    fn new(grid: &'a Grid) -> Self {
+        WordSearch { grid }
+    }
+
+    fn find(&self, word: &str) -> Vec<WordMatch> {
+        self.find_all(&[word])
+    }
+
+    fn find_all(&self, words: &[&str]) -> Vec<WordMatch> {
+        Dictionary::new(words).search(self.grid)
+    }
+}
+
+// `None` cells are wildcards
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Pattern {
+    cells: Vec<Vec<Option<String>>>,
+}
+
+// a pattern spelled in one grid's letters
+type Stencil = Vec<Vec<Option<Letter>>>;
+
+impl std::fmt::Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rows: Vec<String> = self
+            .cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| cell.as_deref().unwrap_or("."))
+                    .collect()
+            })
+            .collect();
+        write!(f, "{}", rows.join(" / "))
+    }
+}
+
+impl Pattern {
+    // grid cells covered by the non-wildcard part of the pattern placed at `start`
+    fn cells_at(&self, start: (usize, usize)) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.cells.iter().enumerate().flat_map(move |(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, cell)| cell.is_some())
+                .map(move |(x, _)| (start.0 + y, start.1 + x))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PatternMatch {
+    // (row, column) of the top-left corner
+    start: (usize, usize),
+    // index into `Pattern::variants`
+    variant: usize,
+}
+
+impl Pattern {
+    // rows are separated by newlines or `/`, `.` matches any letter
+    fn parse(template: &str) -> Self {
+        let cells: Vec<Vec<Option<String>>> = template
+            .split(['\n', '/'])
+            .map(str::trim)
+            .filter(|row| !row.is_empty())
+            .map(|row| {
+                row.graphemes(true)
+                    .map(|letter| (letter != ".").then(|| letter.to_string()))
+                    .collect()
+            })
+            .collect();
+
+        if cells.iter().any(|row| row.len() != cells[0].len()) {
+            panic!("Pattern rows have different widths: {:?}", template);
+        }
+        Pattern { cells }
+    }
+
+    fn width(&self) -> usize {
+        self.cells.first().map_or(0, Vec::len)
+    }
+
+    fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    // 90 degrees clockwise
+    fn rotated(&self) -> Self {
+        let cells = (0..self.width())
+            .map(|x| {
+                (0..self.height())
+                    .rev()
+                    .map(|y| self.cells[y][x].clone())
+                    .collect()
+            })
+            .collect();
+        Pattern { cells }
+    }
+
+    // mirrored left to right
+    fn reflected(&self) -> Self {
+        let cells = self
+            .cells
+            .iter()
+            .map(|row| row.iter().rev().cloned().collect())
+            .collect();
+        Pattern { cells }
+    }
+
+    // all distinct rotations and reflections, starting with the pattern itself
+    fn variants(&self) -> Vec<Pattern> {
+        let mut variants: Vec<Pattern> = vec![];
+        for mut variant in [self.clone(), self.reflected()] {
+            for _ in 0..4 {
+                let next = variant.rotated();
+                if !variants.contains(&variant) {
+                    variants.push(variant);
                 }
+                variant = next;
             }
-            if let Some(block) = if did_break { None } else { Some(curr_block) } {
-                blocks.push(block);
+        }
+        variants
+    }
+
+    // `None` if the pattern needs a letter the grid doesn't have
+    fn stencil(&self, grid: &Grid) -> Option<Stencil> {
+        self.cells
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| match cell {
+                        Some(text) => grid.letter(text).map(Some),
+                        None => Some(None),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    // a wildcard stands for any letter, but not for padding
+    fn matches(stencil: &Stencil, window: &Window) -> bool {
+        let padding = window.grid.letter("");
+        stencil
+            .iter()
+            .zip(window.rows())
+            .all(|(pattern_row, window_row)| {
+                pattern_row
+                    .iter()
+                    .zip(window_row)
+                    .all(|(cell, letter)| match cell {
+                        Some(cell) => cell == letter,
+                        None => Some(*letter) != padding,
+                    })
+            })
+    }
+
+    fn find(&self, grid: &Grid) -> Vec<PatternMatch> {
+        let mut matches = vec![];
+        for (variant_idx, variant) in self.variants().iter().enumerate() {
+            let Some(stencil) = variant.stencil(grid) else {
+                continue;
+            };
+            for window in grid.windows(variant.width(), variant.height()) {
+                if Pattern::matches(&stencil, &window) {
+                    matches.push(PatternMatch {
+                        start: window.start,
+                        variant: variant_idx,
+                    });
+                }
             }
         }
+        matches.sort_by_key(|found| found.start);
+        matches
     }
-    blocks
 }
 
-fn parse_input(input: &str) -> Vec<Vec<char>> {
-    input
-        .lines()
-        .map(|line| line.trim().chars().collect())
-        .collect()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    // part of a word running in this direction
+    Word(Direction),
+    // part of a 2D pattern match
+    Shape,
+    // claimed by marks of different kinds
+    Several,
 }
 
-fn part_1(input: &str) -> usize {
-    let board = parse_input(input);
+impl Mark {
+    fn ansi(self) -> &'static str {
+        match self {
+            Mark::Word(direction) => match direction {
+                Direction::North => "\x1b[1;31m",
+                Direction::NorthEast => "\x1b[1;32m",
+                Direction::East => "\x1b[1;33m",
+                Direction::SouthEast => "\x1b[1;34m",
+                Direction::South => "\x1b[1;35m",
+                Direction::SouthWest => "\x1b[1;36m",
+                Direction::West => "\x1b[1;91m",
+                Direction::NorthWest => "\x1b[1;94m",
+            },
+            Mark::Shape => "\x1b[1;30;43m",
+            Mark::Several => "\x1b[1;97m",
+        }
+    }
 
-    let rows = split_into(&board, SplitKind::Rows);
-    let columns = split_into(&board, SplitKind::Columns);
-    let diagonals_ascending = split_into(&board, SplitKind::Diagonal(Diagonal::Ascending, 4));
-    let diagonals_descending = split_into(&board, SplitKind::Diagonal(Diagonal::Descending, 4));
+    fn css(self) -> &'static str {
+        match self {
+            Mark::Word(direction) => match direction {
+                Direction::North => "color:#d62728;font-weight:bold",
+                Direction::NorthEast => "color:#2ca02c;font-weight:bold",
+                Direction::East => "color:#b8860b;font-weight:bold",
+                Direction::SouthEast => "color:#1f77b4;font-weight:bold",
+                Direction::South => "color:#9467bd;font-weight:bold",
+                Direction::SouthWest => "color:#17becf;font-weight:bold",
+                Direction::West => "color:#ff7f0e;font-weight:bold",
+                Direction::NorthWest => "color:#e377c2;font-weight:bold",
+            },
+            Mark::Shape => "outline:2px solid #e0a000;font-weight:bold",
+            Mark::Several => "color:#000;background:#ddd;font-weight:bold",
+        }
+    }
+}
 
-    let mut cnt = 0;
+// one optional mark per grid cell, row-major like `Grid::cells`
+struct Marks {
+    marks: Vec<Option<Mark>>,
+    width: usize,
+}
 
-    for row in rows.iter() {
-        for window in row.windows(4) {
-            match window {
-                ['X', 'M', 'A', 'S'] | ['S', 'A', 'M', 'X'] => cnt += 1,
-                _ => continue,
-            };
+impl Marks {
+    fn new(grid: &Grid) -> Self {
+        Marks {
+            marks: vec![None; grid.cells.len()],
+            width: grid.width,
         }
     }
 
-    for col in columns.iter() {
-        for window in col.windows(4) {
-            match window {
-                ['X', 'M', 'A', 'S'] | ['S', 'A', 'M', 'X'] => cnt += 1,
-                _ => continue,
-            };
+    fn mark(&mut self, (row, column): (usize, usize), mark: Mark) {
+        let cell = &mut self.marks[row * self.width + column];
+        *cell = match *cell {
+            Some(existing) if existing != mark => Some(Mark::Several),
+            _ => Some(mark),
+        };
+    }
+
+    fn words(mut self, grid: &Grid, words: &[&str], matches: &[WordMatch]) -> Self {
+        for found in matches {
+            for cell in found.cells(grid, words[found.word].graphemes(true).count()) {
+                self.mark(cell, Mark::Word(found.direction));
+            }
         }
+        self
     }
 
-    for diag_asc in diagonals_ascending.iter() {
-        let sl = diag_asc.as_slice();
-        match sl {
-            ['X', 'M', 'A', 'S'] | ['S', 'A', 'M', 'X'] => cnt += 1,
-            _ => continue,
+    fn patterns(mut self, variants: &[Pattern], matches: &[PatternMatch]) -> Self {
+        for found in matches {
+            for cell in variants[found.variant].cells_at(found.start) {
+                self.mark(cell, Mark::Shape);
+            }
+        }
+        self
+    }
+
+    fn get(&self, (row, column): (usize, usize)) -> Option<Mark> {
+        self.marks[row * self.width + column]
+    }
+}
+
+// `dim` grays out letters that are not part of any match
+fn render_ansi(grid: &Grid, marks: &Marks, dim: bool) -> String {
+    const GRAY: &str = "\x1b[90m";
+    const RESET: &str = "\x1b[0m";
+
+    let mut rendered = String::new();
+    for (position, &letter) in grid.positions().zip(grid.cells.iter()) {
+        let letter = match grid.text(letter) {
+            "" => " ",
+            text => text,
         };
+        match marks.get(position) {
+            Some(mark) => rendered.push_str(&format!("{}{letter}{RESET}", mark.ansi())),
+            None if dim => rendered.push_str(&format!("{GRAY}{letter}{RESET}")),
+            None => rendered.push_str(letter),
+        }
+        if position.1 + 1 == grid.width {
+            rendered.push('\n');
+        }
     }
+    rendered
+}
 
-    for diag_dsc in diagonals_descending.iter() {
-        let sl = diag_dsc.as_slice();
-        match sl {
-            ['X', 'M', 'A', 'S'] | ['S', 'A', 'M', 'X'] => cnt += 1,
-            _ => continue,
+fn render_html(grid: &Grid, marks: &Marks, dim: bool) -> String {
+    let mut rendered = String::from(
+        "<table style=\"font-family:monospace;border-collapse:separate;border-spacing:2px\">\n",
+    );
+    for (position, &letter) in grid.positions().zip(grid.cells.iter()) {
+        if position.1 == 0 {
+            rendered.push_str("<tr>");
+        }
+        let style = match marks.get(position) {
+            Some(mark) => mark.css(),
+            None if dim => "color:#bbb",
+            None => "",
         };
+        let letter = grid
+            .text(letter)
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        rendered.push_str(&format!("<td style=\"{style}\">{letter}</td>"));
+        if position.1 + 1 == grid.width {
+            rendered.push_str("</tr>\n");
+        }
     }
+    rendered.push_str("</table>\n");
+    rendered
+}
 
-    cnt
+fn parse_input(input: &str, ragged: Ragged) -> Result<Grid, RaggedRow> {
+    let mut rows: Vec<Vec<&str>> = input
+        .lines()
+        .map(|line| line.trim().graphemes(true).collect())
+        .collect();
+    // blank lines at the end of the file aren't rows
+    while rows.last().is_some_and(Vec::is_empty) {
+        rows.pop();
+    }
+    Grid::from_rows(rows, ragged)
 }
 
-fn part_2(input: &str) -> usize {
-    const LETTER_SEQUENCES: [[char; 4]; 4] = [
-        ['M', 'M', 'S', 'S'],
-        ['S', 'M', 'M', 'S'],
-        ['S', 'S', 'M', 'M'],
-        ['M', 'S', 'S', 'M'],
-    ];
+const XMAS: &str = "XMAS";
+const X_MAS: &str = "M.S / .A. / M.S";
+
+fn part_1(input: &str, ragged: Ragged) -> usize {
+    let grid = parse_input(input, ragged).unwrap_or_else(|err| panic!("{err}"));
 
-    let board = parse_input(input);
-    let blocks = get_blocks(&board, 3, 3);
-    let mut cnt = 0;
-
-    for block in blocks.into_iter() {
-        for seq in LETTER_SEQUENCES {
-            // clockwise winding order
-            if block[1][1] == 'A'
-                && block[0][0] == seq[0]
-                && block[0][2] == seq[1]
-                && block[2][2] == seq[2]
-                && block[2][0] == seq[3]
-            {
-                cnt += 1;
+    WordSearch::new(&grid).find(XMAS).len()
+}
+
+fn part_2(input: &str, ragged: Ragged) -> usize {
+    let grid = parse_input(input, ragged).unwrap_or_else(|err| panic!("{err}"));
+
+    Pattern::parse(X_MAS).find(&grid).len()
+}
+
+// `size` x `size` random XMAS letters for timing the searches
+fn generate_input(size: usize) -> String {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut rows = vec![];
+    for _ in 0..size {
+        let row: String = (0..size)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                ['X', 'M', 'A', 'S'][(state % 4) as usize]
+            })
+            .collect();
+        rows.push(row);
+    }
+    rows.join("\n")
+}
+
+fn main() {
+    let mut input_path = INPUT_PATH.to_string();
+    let mut words = None;
+    let mut pattern = None;
+    let mut bench_size = None;
+    let mut dictionary_path = None;
+    let mut render_part = None;
+    let mut html = false;
+    let mut dim = false;
+    let mut ragged = Ragged::Reject;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input_path = args.next().expect("Missing value for --input"),
+            "--find" => words = Some(args.next().expect("Missing value for --find")),
+            "--pattern" => pattern = Some(args.next().expect("Missing value for --pattern")),
+            "--dictionary" => {
+                dictionary_path = Some(args.next().expect("Missing value for --dictionary"))
             }
+            "--render" => {
+                let part = args.next().expect("Missing value for --render");
+                render_part = Some(part.parse::<u8>().unwrap());
+            }
+            "--html" => html = true,
+            "--dim" => dim = true,
+            "--pad" => ragged = Ragged::Pad,
+            "--bench" => {
+                let size = args.next().expect("Missing value for --bench");
+                bench_size = Some(size.parse::<usize>().unwrap());
+            }
+            _ => panic!("Unexpected argument: {}", arg),
         }
     }
 
-    cnt
-}
+    if let Some(size) = bench_size {
+        let input = &generate_input(size);
+        for (name, part) in [
+            ("Part 1", part_1 as fn(&str, Ragged) -> usize),
+            ("Part 2", part_2),
+        ] {
+            let start = std::time::Instant::now();
+            let res = part(input, ragged);
+            println!("{name}: {res} in {:?} ({size}x{size})", start.elapsed());
+        }
+        return;
+    }
 
-fn main() {
-    let input = &std::fs::read_to_string(INPUT_PATH).unwrap();
+    let input = &std::fs::read_to_string(&input_path).unwrap();
+    let grid = parse_input(input, ragged).unwrap_or_else(|err| panic!("{err}"));
+
+    if let Some(part) = render_part {
+        let marks = match part {
+            1 => Marks::new(&grid).words(&grid, &[XMAS], &WordSearch::new(&grid).find(XMAS)),
+            2 => {
+                let pattern = Pattern::parse(X_MAS);
+                Marks::new(&grid).patterns(&pattern.variants(), &pattern.find(&grid))
+            }
+            _ => panic!("Unknown part: {}", part),
+        };
+        if html {
+            print!("{}", render_html(&grid, &marks, dim));
+        } else {
+            print!("{}", render_ansi(&grid, &marks, dim));
+        }
+        return;
+    }
+
+    if let Some(pattern) = pattern {
+        let pattern = Pattern::parse(&pattern);
+        let variants = pattern.variants();
+        for found in pattern.find(&grid) {
+            println!("Match at {:?}: {}", found.start, variants[found.variant]);
+        }
+        return;
+    }
+
+    if let Some(dictionary_path) = dictionary_path {
+        let dictionary = std::fs::read_to_string(&dictionary_path).unwrap();
+        let words: Vec<&str> = dictionary
+            .lines()
+            .map(str::trim)
+            .filter(|word| !word.is_empty())
+            .collect();
+        let matches = Dictionary::new(&words).search(&grid);
+        for report in report(&grid, &words, &matches) {
+            println!(
+                "{}: {} match(es), {} overlapping",
+                words[report.word],
+                report.matches.len(),
+                report.overlapping
+            );
+            for found in report.matches {
+                println!("    at {:?} going {:?}", found.start, found.direction);
+            }
+        }
+        return;
+    }
+
+    if let Some(words) = words {
+        let words: Vec<&str> = words.split(',').collect();
+        for found in WordSearch::new(&grid).find_all(&words) {
+            println!(
+                "{} at {:?} going {:?}",
+                words[found.word], found.start, found.direction
+            );
+        }
+        return;
+    }
 
-    let part_1_res = part_1(input);
+    let part_1_res = part_1(input, ragged);
     println!("Part 1: {part_1_res}");
 
-    let part_2_res = part_2(input);
+    let part_2_res = part_2(input, ragged);
     println!("Part 2: {part_2_res}")
 }
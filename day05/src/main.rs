@@ -1,4 +1,9 @@
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt::{Debug, Display};
+use std::hash::Hash;
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
 
 #[allow(unused)]
 const TEST_INPUT: &str = "47|53
@@ -33,102 +38,1840 @@ const TEST_INPUT: &str = "47|53
 #[allow(unused)]
 const INPUT_PATH: &str = "input.txt";
 
-type Page = u8;
-type Rules = HashMap<Page, HashSet<Page>>;
-type Update = Vec<Page>;
+// anything pages can be named by; numbers also have an index, which lets
+// the rules between them be stored as bits
+trait PageId: Clone + Hash + Eq + Ord + Debug + Display + FromStr<Err: Debug> {
+    fn index(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl PageId for String {}
+
+macro_rules! numeric_page_id {
+    ($($page:ty),*) => {
+        $(impl PageId for $page {
+            fn index(&self) -> Option<usize> {
+                usize::try_from(*self).ok()
+            }
+        })*
+    };
+}
+
+numeric_page_id!(u8, u16, u32, u64);
+
+type Update<P> = Vec<P>;
+// `a|b`: a has to come before b
+type Rule<P> = (P, P);
+
+// pages numbered up to this get a row of bits each, 32 MiB at most
+const DENSE_LIMIT: usize = 1 << 14;
+// ... as long as the pages in the rules fill at least this share of the
+// numbers, a couple of stray large ids shouldn't cost a full matrix
+const DENSE_FILL: usize = 8;
+
+#[derive(Debug, Clone)]
+enum Rules<P> {
+    Sparse(HashMap<P, HashSet<P>>),
+    // for pages with small indices
+    Dense {
+        // bit `b % 64` of `bits[a * words + b / 64]` is set for rule a|b
+        bits: Vec<u64>,
+        words: usize,
+        // pages in any rule, by index
+        pages: Vec<Option<P>>,
+    },
+}
+
+impl<P: PageId> Rules<P> {
+    fn from_list(rule_list: &[Rule<P>]) -> Self {
+        let indices: Option<HashSet<usize>> = rule_list
+            .iter()
+            .flat_map(|(a, b)| [a, b])
+            .map(|page| page.index())
+            .collect();
+        let size = indices.as_ref().and_then(|indices| {
+            let size = indices.iter().max().map_or(Some(0), |i| i.checked_add(1))?;
+            (size <= DENSE_LIMIT && size <= DENSE_FILL * indices.len().max(64)).then_some(size)
+        });
+        let mut rules = match size {
+            Some(size) => Rules::dense(size),
+            None => Rules::Sparse(HashMap::new()),
+        };
+        for (a, b) in rule_list {
+            rules.insert(a.clone(), b.clone());
+        }
+        rules
+    }
+
+    fn dense(size: usize) -> Self {
+        let words = size.div_ceil(64);
+        Rules::Dense {
+            bits: vec![0; size * words],
+            words,
+            pages: vec![None; size],
+        }
+    }
+
+    // no rules, but room for the same pages
+    fn empty_like(&self) -> Self {
+        match self {
+            Rules::Sparse(_) => Rules::Sparse(HashMap::new()),
+            Rules::Dense { pages, .. } => Rules::dense(pages.len()),
+        }
+    }
+
+    fn insert(&mut self, a: P, b: P) {
+        match self {
+            Rules::Sparse(rules) => {
+                rules.entry(a).or_default().insert(b);
+            }
+            Rules::Dense { bits, words, pages } => {
+                let (i, j) = (a.index().unwrap(), b.index().unwrap());
+                bits[i * *words + j / 64] |= 1 << (j % 64);
+                pages[i] = Some(a);
+                pages[j] = Some(b);
+            }
+        }
+    }
+
+    fn contains(&self, a: &P, b: &P) -> bool {
+        match self {
+            Rules::Sparse(rules) => rules.get(a).is_some_and(|after| after.contains(b)),
+            Rules::Dense { bits, words, pages } => match (a.index(), b.index()) {
+                (Some(i), Some(j)) if i < pages.len() && j < pages.len() => {
+                    bits[i * words + j / 64] >> (j % 64) & 1 == 1
+                }
+                _ => false,
+            },
+        }
+    }
+
+    // `ruled[i * pages.len() + j]`: a rule puts `pages[i]` before `pages[j]`
+    fn between(&self, pages: &[P]) -> Vec<bool> {
+        let n = pages.len();
+        let mut ruled = vec![false; n * n];
+        match self {
+            Rules::Sparse(_) => {
+                for (i, a) in pages.iter().enumerate() {
+                    for (j, b) in pages.iter().enumerate() {
+                        ruled[i * n + j] = self.contains(a, b);
+                    }
+                }
+            }
+            Rules::Dense {
+                bits,
+                words,
+                pages: known,
+            } => {
+                let indices: Vec<Option<usize>> = pages
+                    .iter()
+                    .map(|page| page.index().filter(|&i| i < known.len()))
+                    .collect();
+                for (i, a) in indices.iter().enumerate() {
+                    let Some(a) = a else { continue };
+                    for (j, b) in indices.iter().enumerate() {
+                        if let Some(b) = b {
+                            ruled[i * n + j] = bits[a * words + b / 64] >> (b % 64) & 1 == 1;
+                        }
+                    }
+                }
+            }
+        }
+        ruled
+    }
+
+    // sorted, so everything built from them comes out in the same order every
+    // run; indices already are in the same order as the numbers
+    fn after(&self, a: &P) -> Vec<P> {
+        match self {
+            Rules::Sparse(rules) => {
+                let mut after: Vec<P> = rules.get(a).into_iter().flatten().cloned().collect();
+                after.sort_unstable();
+                after
+            }
+            Rules::Dense { bits, words, pages } => {
+                let Some(i) = a.index().filter(|&i| i < pages.len()) else {
+                    return vec![];
+                };
+                let mut after = vec![];
+                for (w, &word) in bits[i * words..(i + 1) * words].iter().enumerate() {
+                    let mut word = word;
+                    while word != 0 {
+                        let j = w * 64 + word.trailing_zeros() as usize;
+                        after.push(pages[j].clone().unwrap());
+                        word &= word - 1;
+                    }
+                }
+                after
+            }
+        }
+    }
+
+    // every page on either side of a rule, sorted
+    fn pages(&self) -> Vec<P> {
+        match self {
+            Rules::Sparse(rules) => {
+                let mut pages: Vec<P> = rules
+                    .iter()
+                    .flat_map(|(page, after)| std::iter::once(page).chain(after))
+                    .cloned()
+                    .collect();
+                pages.sort_unstable();
+                pages.dedup();
+                pages
+            }
+            Rules::Dense { pages, .. } => pages.iter().flatten().cloned().collect(),
+        }
+    }
 
-fn parse_input(input: &str) -> (Rules, Vec<Update>) {
+    fn reversed(&self) -> Self {
+        let mut reversed = self.empty_like();
+        for page in self.pages() {
+            for next in self.after(&page) {
+                reversed.insert(next, page.clone());
+            }
+        }
+        reversed
+    }
+}
+
+fn parse_rule<P: PageId>(rule: &str) -> Rule<P> {
+    let (pre, post) = rule.trim().split_once('|').unwrap();
+    (pre.trim().parse().unwrap(), post.trim().parse().unwrap())
+}
+
+// rules exactly as written, duplicates included, one per line from line 1
+fn parse_sections<P: PageId>(input: &str) -> (Vec<Rule<P>>, Vec<Update<P>>) {
     #[cfg(windows)]
     const DOUBLE_LINE_ENDING: &str = "\r\n\r\n";
     #[cfg(not(windows))]
-    const DOUBLE_LINE_ENDING: &'static str = "\n\n";
+    const DOUBLE_LINE_ENDING: &str = "\n\n";
     let (rules_str, updates_str) = input.split_once(DOUBLE_LINE_ENDING).unwrap();
 
-    let mut rules = Rules::new();
-    let updates: Vec<Update> = updates_str
+    let updates: Vec<Update<P>> = updates_str
         .lines()
         .map(|line| {
             line.split(',')
-                .map(|value| value.parse().unwrap())
+                .map(|value| value.trim().parse().unwrap())
                 .collect()
         })
         .collect();
 
-    for (k, v) in rules_str.lines().map(|rule| {
-        let (pre, post) = rule.trim().split_once('|').unwrap();
-        (pre.trim().parse().unwrap(), post.trim().parse().unwrap())
-    }) {
-        if let std::collections::hash_map::Entry::Vacant(e) = rules.entry(k) {
-            let mut rule_set = HashSet::new();
-            rule_set.insert(v);
-            e.insert(rule_set);
-        } else {
-            rules.get_mut(&k).unwrap().insert(v);
+    let rule_list = rules_str.lines().map(parse_rule).collect();
+
+    (rule_list, updates)
+}
+
+fn parse_input<P: PageId>(input: &str) -> (Rules<P>, Vec<Update<P>>) {
+    let (rule_list, updates) = parse_sections(input);
+
+    (Rules::from_list(&rule_list), updates)
+}
+
+// bits of the narrowest unsigned type that holds every page, `None` if some
+// page is not a number and pages have to be kept as text
+fn page_width(input: &str) -> Option<u32> {
+    let mut largest = 0;
+    for page in input
+        .split(['|', ',', '\n'])
+        .map(str::trim)
+        .filter(|page| !page.is_empty())
+    {
+        largest = page.parse::<u64>().ok()?.max(largest);
+    }
+    let bits = 64 - largest.leading_zeros();
+    Some(bits.next_power_of_two().max(8))
+}
+
+// a page is out of order if a page it has to come before is somewhere earlier
+// in the update. Dense rules take one AND per page and word of the pages seen
+// so far, sparse ones one lookup in a position index of the update per rule
+// leaving its pages
+fn is_correct<P: PageId>(update: &[P], rules: &Rules<P>) -> bool {
+    match rules {
+        Rules::Dense { bits, words, pages } => {
+            // the pages so far as a row of their own, with the words that
+            // have bits set in it; a page is out of order if its row shares
+            // a bit with it. Pages in no rule can't be out of order
+            let mut seen = vec![0u64; *words];
+            let mut touched = vec![];
+            for i in update
+                .iter()
+                .filter_map(|page| page.index().filter(|&i| i < pages.len()))
+            {
+                let row = i * words;
+                if touched
+                    .iter()
+                    .any(|&word| bits[row + word] & seen[word] != 0)
+                {
+                    return false;
+                }
+                if seen[i / 64] == 0 {
+                    touched.push(i / 64);
+                }
+                seen[i / 64] |= 1 << (i % 64);
+            }
+            true
+        }
+        Rules::Sparse(rules) => {
+            // by first position, so a repeated page counts from where it starts
+            let mut positions: HashMap<&P, usize> = HashMap::new();
+            for (i, page) in update.iter().enumerate() {
+                positions.entry(page).or_insert(i);
+            }
+            update.iter().enumerate().all(|(i, page)| {
+                rules
+                    .get(page)
+                    .into_iter()
+                    .flatten()
+                    .all(|next| positions.get(next).is_none_or(|&j| j >= i))
+            })
         }
     }
+}
+
+// pages whose rules contradict each other, each one required before the next
+// and the last one before the first
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Cycle<P>(Vec<P>);
 
-    (rules, updates)
+impl<P: Display> Display for Cycle<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rules: Vec<String> = (0..self.0.len())
+            .map(|i| format!("{}|{}", self.0[i], self.0[(i + 1) % self.0.len()]))
+            .collect();
+        write!(f, "Contradictory rules: {}", rules.join(", "))
+    }
 }
 
-fn is_correct(update: &[Page], rules: &Rules) -> bool {
-    for i in 0..update.len() {
-        if let Some(rule_set) = rules.get(&update[i]) {
-            for elem in &update[..i] {
-                if rule_set.contains(elem) {
-                    return false;
+// Kahn's algorithm over positions `0..len`, `after(i, j)` meaning i has to
+// come before j; of the positions free to go next the lowest goes first.
+// Fails with the positions on a cycle, each one required before the next.
+// A rule `a|a` only binds two copies of a, never a position to itself
+fn kahn(len: usize, after: impl Fn(usize, usize) -> bool) -> Result<Vec<usize>, Vec<usize>> {
+    let after = |i: usize, j: usize| i != j && after(i, j);
+    let mut before_count = vec![0; len];
+    for i in 0..len {
+        for (j, count) in before_count.iter_mut().enumerate() {
+            if after(i, j) {
+                *count += 1;
+            }
+        }
+    }
+
+    let mut ready: BinaryHeap<Reverse<usize>> = (0..len)
+        .filter(|&i| before_count[i] == 0)
+        .map(Reverse)
+        .collect();
+    let mut order = Vec::with_capacity(len);
+    while let Some(Reverse(i)) = ready.pop() {
+        order.push(i);
+        for (j, count) in before_count.iter_mut().enumerate() {
+            if after(i, j) {
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(Reverse(j));
+                }
+            }
+        }
+    }
+
+    if order.len() == len {
+        return Ok(order);
+    }
+
+    // every position left has a predecessor that is also left, so walking
+    // backwards from any of them has to come round to one seen before
+    let mut seen = vec![None; len];
+    let mut path = vec![];
+    let mut at = (0..len).find(|&i| before_count[i] > 0).unwrap();
+    while seen[at].is_none() {
+        seen[at] = Some(path.len());
+        path.push(at);
+        at = (0..len)
+            .find(|&i| before_count[i] > 0 && after(i, at))
+            .unwrap();
+    }
+    let mut cycle = path.split_off(seen[at].unwrap());
+    cycle.reverse();
+    Err(cycle)
+}
+
+// Kahn's algorithm on the rules between the update's pages; of the pages that
+// are free to go next, the one that came first in the update goes first
+fn fix<P: PageId>(update: &[P], rules: &Rules<P>) -> Result<Update<P>, Cycle<P>> {
+    let pages =
+        |order: Vec<usize>| -> Vec<P> { order.into_iter().map(|i| update[i].clone()).collect() };
+    let ruled = rules.between(update);
+    match kahn(update.len(), |i, j| ruled[i * update.len() + j]) {
+        Ok(order) => Ok(pages(order)),
+        Err(cycle) => Err(Cycle(pages(cycle))),
+    }
+}
+
+// strongly connected components of the rules with at least `min_size` pages,
+// found with Kosaraju's algorithm
+fn tangles<P: PageId>(rules: &Rules<P>, min_size: usize) -> Vec<Vec<P>> {
+    // pages by the time their depth first search finished
+    let mut finished = vec![];
+    let mut visited = HashSet::new();
+    for root in rules.pages() {
+        if !visited.insert(root.clone()) {
+            continue;
+        }
+        let after = rules.after(&root).into_iter();
+        let mut stack = vec![(root, after)];
+        while let Some((page, after)) = stack.last_mut() {
+            match after.next() {
+                Some(next) => {
+                    if visited.insert(next.clone()) {
+                        let after = rules.after(&next).into_iter();
+                        stack.push((next, after));
+                    }
+                }
+                None => {
+                    finished.push(page.clone());
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    let reversed = rules.reversed();
+    let mut components = vec![];
+    let mut assigned = HashSet::new();
+    for root in finished.into_iter().rev() {
+        if !assigned.insert(root.clone()) {
+            continue;
+        }
+        let mut component = vec![root.clone()];
+        let mut stack = vec![root];
+        while let Some(page) = stack.pop() {
+            for next in reversed.after(&page) {
+                if assigned.insert(next.clone()) {
+                    component.push(next.clone());
+                    stack.push(next);
+                }
+            }
+        }
+        if component.len() >= min_size {
+            component.sort_unstable();
+            components.push(component);
+        }
+    }
+    components.sort();
+    components
+}
+
+// a cycle of three pages or more inside `component`: the first rule a|b that
+// has a way from b back to a other than b|a, closed the shortest way round.
+// `None` if only pairs of contradicting rules hold the component together
+fn long_cycle<P: PageId>(rules: &Rules<P>, component: &[P]) -> Option<Cycle<P>> {
+    let inside = |page: &P| component.binary_search(page).is_ok();
+    for start in component {
+        for first in rules.after(start) {
+            if first == *start || !inside(&first) {
+                continue;
+            }
+            let mut previous: HashMap<P, P> = HashMap::new();
+            let mut queue = VecDeque::from([first.clone()]);
+            while let Some(page) = queue.pop_front() {
+                for next in rules.after(&page) {
+                    if !inside(&next) || (page == first && next == *start) {
+                        continue;
+                    }
+                    if next == *start {
+                        let mut cycle = vec![page];
+                        while let Some(before) = previous.get(cycle.last().unwrap()) {
+                            cycle.push(before.clone());
+                        }
+                        cycle.push(start.clone());
+                        cycle.reverse();
+                        return Some(Cycle(cycle));
+                    }
+                    if next == first {
+                        continue;
+                    }
+                    if let std::collections::hash_map::Entry::Vacant(e) =
+                        previous.entry(next.clone())
+                    {
+                        e.insert(page.clone());
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Lint<P> {
+    // the same rule on more than one line
+    Duplicate {
+        rule: Rule<P>,
+        lines: Vec<usize>,
+    },
+    // `a|b` and `b|a`, by the first line of each
+    Contradiction {
+        rule: Rule<P>,
+        lines: (usize, usize),
+    },
+    // `a|a`, by its first line
+    SelfRule {
+        page: P,
+        line: usize,
+    },
+    // pages that all have to come before each other, longer than a contradiction
+    Tangle {
+        pages: Vec<P>,
+        cycle: Cycle<P>,
+    },
+    // a page in updates that no rule mentions
+    Unruled {
+        page: P,
+        lines: Vec<usize>,
+    },
+    // an update whose own pages can't be put in any order
+    Unsortable {
+        line: usize,
+        cycle: Cycle<P>,
+    },
+    // an update with more than one valid order, `pages` being free to swap
+    Ambiguous {
+        line: usize,
+        pages: (P, P),
+    },
+}
+
+impl<P: Display> Display for Lint<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let join = |items: &[usize]| {
+            let items: Vec<String> = items.iter().map(usize::to_string).collect();
+            items.join(", ")
+        };
+        match self {
+            Lint::Duplicate { rule, lines } => write!(
+                f,
+                "Rule {}|{} is repeated on lines {}",
+                rule.0,
+                rule.1,
+                join(lines)
+            ),
+            Lint::Contradiction { rule, lines } => write!(
+                f,
+                "Rule {}|{} on line {} contradicts {}|{} on line {}",
+                rule.0, rule.1, lines.0, rule.1, rule.0, lines.1
+            ),
+            Lint::SelfRule { page, line } => {
+                write!(
+                    f,
+                    "Rule {page}|{page} on line {line} puts page {page} before itself"
+                )
+            }
+            Lint::Tangle { pages, cycle } => write!(
+                f,
+                "Page {} and {} others form cycles. {cycle}",
+                pages[0],
+                pages.len() - 1
+            ),
+            Lint::Unruled { page, lines } => write!(
+                f,
+                "Page {page} is not in any rule but is in the updates on lines {}",
+                join(lines)
+            ),
+            Lint::Unsortable { line, cycle } => {
+                write!(f, "Update on line {line} has no valid order. {cycle}")
+            }
+            Lint::Ambiguous { line, pages } => write!(
+                f,
+                "Update on line {line} has more than one valid order, no rule orders {} and {}",
+                pages.0, pages.1
+            ),
+        }
+    }
+}
+
+fn lint<P: PageId>(rule_list: &[Rule<P>], updates: &[Update<P>]) -> Vec<Lint<P>> {
+    let mut lints = vec![];
+
+    // lines of every distinct rule, in order of first appearance
+    let mut rule_lines: Vec<(Rule<P>, Vec<usize>)> = vec![];
+    let mut index: HashMap<Rule<P>, usize> = HashMap::new();
+    for (i, rule) in rule_list.iter().enumerate() {
+        let idx = *index.entry(rule.clone()).or_insert_with(|| {
+            rule_lines.push((rule.clone(), vec![]));
+            rule_lines.len() - 1
+        });
+        rule_lines[idx].1.push(i + 1);
+    }
+
+    for (rule, lines) in rule_lines.iter() {
+        if lines.len() > 1 {
+            lints.push(Lint::Duplicate {
+                rule: rule.clone(),
+                lines: lines.clone(),
+            });
+        }
+    }
+
+    for (idx, ((a, b), lines)) in rule_lines.iter().enumerate() {
+        if a == b {
+            lints.push(Lint::SelfRule {
+                page: a.clone(),
+                line: lines[0],
+            });
+        } else if let Some(&reverse_idx) = index.get(&(b.clone(), a.clone())) {
+            if reverse_idx > idx {
+                lints.push(Lint::Contradiction {
+                    rule: (a.clone(), b.clone()),
+                    lines: (lines[0], rule_lines[reverse_idx].1[0]),
+                });
+            }
+        }
+    }
+
+    let rules = Rules::from_list(rule_list);
+
+    // components that only contradictions hold together, two page ones
+    // among them, are already reported above
+    for pages in tangles(&rules, 3) {
+        if let Some(cycle) = long_cycle(&rules, &pages) {
+            lints.push(Lint::Tangle { pages, cycle });
+        }
+    }
+
+    // updates start after the rules and the blank line
+    let first_update_line = rule_list.len() + 2;
+
+    let ruled: HashSet<&P> = rule_list.iter().flat_map(|(a, b)| [a, b]).collect();
+    let mut unruled: HashMap<P, Vec<usize>> = HashMap::new();
+    for (i, update) in updates.iter().enumerate() {
+        for page in update {
+            if !ruled.contains(page) {
+                let lines = unruled.entry(page.clone()).or_default();
+                if lines.last() != Some(&(first_update_line + i)) {
+                    lines.push(first_update_line + i);
+                }
+            }
+        }
+    }
+    let mut unruled: Vec<(P, Vec<usize>)> = unruled.into_iter().collect();
+    unruled.sort();
+    for (page, lines) in unruled {
+        lints.push(Lint::Unruled { page, lines });
+    }
+
+    for (i, update) in updates.iter().enumerate() {
+        let line = first_update_line + i;
+        match fix(update, &rules) {
+            Err(cycle) => lints.push(Lint::Unsortable { line, cycle }),
+            Ok(fixed) => {
+                // a valid order is the only one exactly when each page is
+                // ruled to come before the next
+                let free = fixed
+                    .windows(2)
+                    .find(|pair| !rules.contains(&pair[0], &pair[1]));
+                if let Some(pair) = free {
+                    lints.push(Lint::Ambiguous {
+                        line,
+                        pages: (pair[0].clone(), pair[1].clone()),
+                    });
+                }
+            }
+        }
+    }
+
+    lints
+}
+
+// a rule broken by an update: the page at `positions.1` has to come before
+// the one at `positions.0`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Violation<P> {
+    positions: (usize, usize),
+    rule: Rule<P>,
+    // first line the rule is written on
+    line: usize,
+}
+
+struct RuleGraph<P> {
+    rules: Rules<P>,
+    lines: HashMap<Rule<P>, usize>,
+    // every page reachable from each page through any chain of rules
+    reachable: Rules<P>,
+}
+
+impl<P: PageId> RuleGraph<P> {
+    fn new(rule_list: &[Rule<P>]) -> Self {
+        let rules = Rules::from_list(rule_list);
+        let mut lines = HashMap::new();
+        for (i, rule) in rule_list.iter().enumerate() {
+            lines.entry(rule.clone()).or_insert(i + 1);
+        }
+
+        let mut reachable = rules.empty_like();
+        for page in rules.pages() {
+            let mut seen = HashSet::new();
+            let mut stack = vec![page.clone()];
+            while let Some(at) = stack.pop() {
+                for next in rules.after(&at) {
+                    if seen.insert(next.clone()) {
+                        reachable.insert(page.clone(), next.clone());
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+
+        RuleGraph {
+            rules,
+            lines,
+            reachable,
+        }
+    }
+
+    // through any chain of rules, so pages on a cycle precede each other
+    fn must_precede(&self, a: &P, b: &P) -> bool {
+        self.reachable.contains(a, b)
+    }
+
+    fn rule(&self, a: &P, b: &P) -> Option<Rule<P>> {
+        self.rules.contains(a, b).then(|| (a.clone(), b.clone()))
+    }
+
+    // every order of `pages` that keeps the rules between them, like
+    // `is_correct`, generated lazily
+    fn all_valid_orderings(&self, pages: &[P]) -> Orderings<P> {
+        let after: Vec<Vec<usize>> = (0..pages.len())
+            .map(|i| {
+                (0..pages.len())
+                    .filter(|&j| self.rules.contains(&pages[i], &pages[j]))
+                    .collect()
+            })
+            .collect();
+        let mut before_count = vec![0; pages.len()];
+        for &j in after.iter().flatten() {
+            before_count[j] += 1;
+        }
+
+        Orderings {
+            pages: pages.to_vec(),
+            after,
+            before_count,
+            placed: vec![false; pages.len()],
+            order: vec![],
+            cursors: vec![0],
+            done: false,
+        }
+    }
+
+    fn explain(&self, update: &[P]) -> Vec<Violation<P>> {
+        let mut violations = vec![];
+        for j in 0..update.len() {
+            for i in 0..j {
+                if let Some(rule) = self.rule(&update[j], &update[i]) {
+                    violations.push(Violation {
+                        positions: (i, j),
+                        line: self.lines[&rule],
+                        rule,
+                    });
+                }
+            }
+        }
+        violations.sort_by_key(|violation| violation.positions);
+        violations
+    }
+
+    // leaves out violations that follow from a chain of two others through a
+    // page in between: if k has to go before i and j before k, j before i is
+    // already implied
+    fn minimal_violations(&self, update: &[P]) -> Vec<Violation<P>> {
+        let violations = self.explain(update);
+        let violated: HashSet<(usize, usize)> = violations
+            .iter()
+            .map(|violation| violation.positions)
+            .collect();
+        violations
+            .into_iter()
+            .filter(|violation| {
+                let (i, j) = violation.positions;
+                !(i + 1..j).any(|k| violated.contains(&(i, k)) && violated.contains(&(k, j)))
+            })
+            .collect()
+    }
+}
+
+// backtracking over topological orders, one page position per depth
+struct Orderings<P> {
+    pages: Vec<P>,
+    after: Vec<Vec<usize>>,
+    before_count: Vec<usize>,
+    placed: Vec<bool>,
+    order: Vec<usize>,
+    // next candidate to try at each depth up to the current one
+    cursors: Vec<usize>,
+    done: bool,
+}
+
+impl<P> Orderings<P> {
+    fn place(&mut self, i: usize) {
+        self.placed[i] = true;
+        for &j in self.after[i].iter() {
+            self.before_count[j] -= 1;
+        }
+        self.order.push(i);
+    }
+
+    fn unplace_last(&mut self) {
+        let i = self.order.pop().unwrap();
+        self.placed[i] = false;
+        for &j in self.after[i].iter() {
+            self.before_count[j] += 1;
+        }
+    }
+}
+
+impl<P: Clone> Iterator for Orderings<P> {
+    type Item = Vec<P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let depth = self.order.len();
+            if depth == self.pages.len() {
+                let ordering = self.order.iter().map(|&i| self.pages[i].clone()).collect();
+                self.cursors.pop();
+                if self.order.is_empty() {
+                    self.done = true;
+                } else {
+                    self.unplace_last();
+                }
+                return Some(ordering);
+            }
+
+            let candidate = (self.cursors[depth]..self.pages.len())
+                .find(|&i| !self.placed[i] && self.before_count[i] == 0);
+            match candidate {
+                Some(i) => {
+                    self.cursors[depth] = i + 1;
+                    self.place(i);
+                    self.cursors.push(0);
+                }
+                None => {
+                    self.cursors.pop();
+                    if self.order.is_empty() {
+                        self.done = true;
+                    } else {
+                        self.unplace_last();
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Edit<P> {
+    // take `page` out at `from` and put it back in at `to`
+    Move { page: P, from: usize, to: usize },
+    // exchange the pages at `at` and `at + 1`
+    Swap { at: usize, pages: (P, P) },
+}
+
+impl<P: Display> Display for Edit<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Edit::Move { page, from, to } => write!(f, "move {page} from {from} to {to}"),
+            Edit::Swap { at, pages } => {
+                write!(f, "swap {} and {} at {at}", pages.0, pages.1)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditKind {
+    Moves,
+    Swaps,
+}
+
+// the swap search keeps one state per set of pages placed so far, give up
+// once there are more than this
+const MAX_SWAP_STATES: usize = 1 << 18;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RepairError<P> {
+    Cycle(Cycle<P>),
+    TooLarge { pages: usize },
+}
+
+impl<P> From<Cycle<P>> for RepairError<P> {
+    fn from(cycle: Cycle<P>) -> Self {
+        RepairError::Cycle(cycle)
+    }
+}
+
+impl<P: Display> Display for RepairError<P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepairError::Cycle(cycle) => write!(f, "has no valid order. {cycle}"),
+            RepairError::TooLarge { pages } => {
+                write!(f, "is too large to repair by swaps ({pages} pages)")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Repair<P> {
+    // applied one after the other, positions as they are at that point
+    edits: Vec<Edit<P>>,
+    repaired: Update<P>,
+}
+
+// `must_precede[i][j]`: the page at i has to come before the one at j through
+// a chain of rules between the update's pages; `None` if there is a cycle
+fn precedence<P: PageId>(update: &[P], rules: &Rules<P>) -> Result<Vec<Vec<bool>>, Cycle<P>> {
+    fix(update, rules)?;
+    let mut must_precede = vec![vec![false; update.len()]; update.len()];
+    for (i, row) in must_precede.iter_mut().enumerate() {
+        let mut stack = vec![i];
+        while let Some(at) = stack.pop() {
+            for next in 0..update.len() {
+                if !row[next] && rules.contains(&update[at], &update[next]) {
+                    row[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+    }
+    Ok(must_precede)
+}
+
+// as few single page moves as possible: the pages that stay are the largest
+// set with no pair out of order. Pairs that are out of order form a partial
+// order, so that is its largest antichain, which by Dilworth and König falls
+// out of a maximum matching
+fn repair_by_moves<P: PageId>(update: &[P], rules: &Rules<P>) -> Result<Repair<P>, Cycle<P>> {
+    let must_precede = precedence(update, rules)?;
+    let n = update.len();
+    let out_of_order = |i: usize, j: usize| i < j && must_precede[j][i];
+
+    // `matched[j]`: the earlier position matched to j
+    let mut matched: Vec<Option<usize>> = vec![None; n];
+    fn augment(
+        i: usize,
+        n: usize,
+        out_of_order: &dyn Fn(usize, usize) -> bool,
+        visited: &mut [bool],
+        matched: &mut [Option<usize>],
+    ) -> bool {
+        for j in 0..n {
+            if out_of_order(i, j) && !visited[j] {
+                visited[j] = true;
+                if matched[j].is_none_or(|k| augment(k, n, out_of_order, visited, matched)) {
+                    matched[j] = Some(i);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+    let is_matched: Vec<bool> = (0..n)
+        .map(|i| augment(i, n, &out_of_order, &mut vec![false; n], &mut matched))
+        .collect();
+
+    // König: alternating paths from the unmatched left side give the minimum
+    // vertex cover, and positions with neither copy in it stay put
+    let mut left = vec![false; n];
+    let mut right = vec![false; n];
+    let mut stack: Vec<usize> = (0..n).filter(|&i| !is_matched[i]).collect();
+    for &i in stack.iter() {
+        left[i] = true;
+    }
+    while let Some(i) = stack.pop() {
+        for j in 0..n {
+            if out_of_order(i, j) && !right[j] {
+                right[j] = true;
+                if let Some(k) = matched[j] {
+                    if !left[k] {
+                        left[k] = true;
+                        stack.push(k);
+                    }
                 }
             }
         }
     }
-    true
+    let stays: Vec<usize> = (0..n).filter(|&i| left[i] && !right[i]).collect();
+
+    // the pages that stay keep their order, the rest go wherever fix puts them
+    let chained = |i: usize, j: usize| stays.windows(2).any(|pair| pair == [i, j]);
+    let order = kahn(n, |i, j| must_precede[i][j] || chained(i, j)).unwrap();
+    let repaired: Update<P> = order.iter().map(|&i| update[i].clone()).collect();
+
+    // moved pages in their final order, each placed right after the page
+    // before it in the result, which is already where it belongs
+    let mut current: Vec<usize> = (0..n).collect();
+    let mut edits = vec![];
+    for (k, &i) in order.iter().enumerate() {
+        if stays.contains(&i) {
+            continue;
+        }
+        let from = current.iter().position(|&at| at == i).unwrap();
+        current.remove(from);
+        let to = match k {
+            0 => 0,
+            _ => current.iter().position(|&at| at == order[k - 1]).unwrap() + 1,
+        };
+        current.insert(to, i);
+        edits.push(Edit::Move {
+            page: update[i].clone(),
+            from,
+            to,
+        });
+    }
+
+    Ok(Repair { edits, repaired })
 }
 
-fn fix(update: &mut [Page], rules: &Rules) {
-    for i in 0..update.len() {
-        if let Some(rule_set) = rules.get(&update[i]) {
-            for j in 0..i {
-                if rule_set.contains(&update[j]) {
-                    update.swap(i, j);
+// as few adjacent swaps as possible, that is the valid order with the fewest
+// pairs reversed; dynamic programming over the sets of pages that can be
+// placed first, which stays small when the rules order most pairs. When it
+// doesn't, the update is reported as too large instead
+fn repair_by_swaps<P: PageId>(update: &[P], rules: &Rules<P>) -> Result<Repair<P>, RepairError<P>> {
+    let must_precede = precedence(update, rules)?;
+    let n = update.len();
+    if n > 64 {
+        return Err(RepairError::TooLarge { pages: n });
+    }
+    let bit = |i: usize| 1u64 << i;
+    let required: Vec<u64> = (0..n)
+        .map(|j| (0..n).filter(|&i| must_precede[i][j]).map(bit).sum())
+        .collect();
+
+    // best cost of each placed set, with the set before it and the page added
+    let mut best: HashMap<u64, (usize, u64, usize)> = HashMap::new();
+    let mut layer = std::collections::BTreeMap::from([(0u64, 0usize)]);
+    for _ in 0..n {
+        let mut next_layer = std::collections::BTreeMap::new();
+        for (&placed, &cost) in layer.iter() {
+            for i in (0..n).filter(|&i| placed & bit(i) == 0 && required[i] & !placed == 0) {
+                // every earlier page not placed yet ends up after this one
+                let cost = cost + (!placed & (bit(i) - 1)).count_ones() as usize;
+                let next = placed | bit(i);
+                if best.get(&next).is_none_or(|&(known, _, _)| cost < known) {
+                    best.insert(next, (cost, placed, i));
+                    next_layer.insert(next, cost);
+                }
+                if best.len() > MAX_SWAP_STATES {
+                    return Err(RepairError::TooLarge { pages: n });
                 }
             }
         }
+        layer = next_layer;
+    }
+
+    let mut order = vec![];
+    let mut placed = if n == 64 { u64::MAX } else { bit(n) - 1 };
+    while placed != 0 {
+        let (_, before, i) = best[&placed];
+        order.push(i);
+        placed = before;
+    }
+    order.reverse();
+
+    // bubble each page of the result into place from the left
+    let mut current: Vec<usize> = (0..n).collect();
+    let mut edits = vec![];
+    for (k, &i) in order.iter().enumerate() {
+        let mut at = current.iter().position(|&at| at == i).unwrap();
+        while at > k {
+            current.swap(at - 1, at);
+            edits.push(Edit::Swap {
+                at: at - 1,
+                pages: (update[current[at]].clone(), update[i].clone()),
+            });
+            at -= 1;
+        }
+    }
+
+    let repaired = order.iter().map(|&i| update[i].clone()).collect();
+    Ok(Repair { edits, repaired })
+}
+
+fn dot_id<P: Display>(page: &P) -> String {
+    format!("{:?}", page.to_string())
+}
+
+// the rules as a Graphviz digraph, either all of them or only those between
+// the pages of `update`, whose broken rules are drawn in red and whose pages
+// are labelled with their position before and after `fix`; `reduce` drops
+// every unbroken rule that also follows from a chain of other rules
+fn to_dot<P: PageId>(rules: &Rules<P>, update: Option<&[P]>, reduce: bool) -> String {
+    let mut pages = match update {
+        Some(update) => update.to_vec(),
+        None => rules.pages(),
+    };
+    pages.sort_unstable();
+    pages.dedup();
+    let index: HashMap<&P, usize> = pages
+        .iter()
+        .enumerate()
+        .map(|(i, page)| (page, i))
+        .collect();
+
+    let position = |page: &P| update.and_then(|update| update.iter().position(|p| p == page));
+    let broken = |a: usize, b: usize| match (position(&pages[a]), position(&pages[b])) {
+        (Some(a), Some(b)) => b < a,
+        _ => false,
+    };
+
+    let mut edges: Vec<(usize, usize)> = vec![];
+    for (a, page) in pages.iter().enumerate() {
+        for next in rules.after(page) {
+            if let Some(&b) = index.get(&next) {
+                edges.push((a, b));
+            }
+        }
+    }
+
+    if reduce {
+        // one edge at a time, so cycles lose only what the rest still implies
+        let mut kept: HashSet<(usize, usize)> = edges.iter().copied().collect();
+        for &(a, b) in edges.iter() {
+            if broken(a, b) {
+                continue;
+            }
+            kept.remove(&(a, b));
+            let mut seen = vec![false; pages.len()];
+            let mut stack = vec![a];
+            while let Some(at) = stack.pop() {
+                for (next, seen) in seen.iter_mut().enumerate() {
+                    if !*seen && kept.contains(&(at, next)) {
+                        *seen = true;
+                        stack.push(next);
+                    }
+                }
+            }
+            if !seen[b] {
+                kept.insert((a, b));
+            }
+        }
+        edges.retain(|edge| kept.contains(edge));
+    }
+
+    let mut dot = String::from("digraph rules {\n    rankdir=LR;\n    node [shape=box];\n");
+    let mut labels: HashMap<usize, String> = HashMap::new();
+    if let Some(update) = update {
+        let caption = match fix(update, rules) {
+            Ok(fixed) => {
+                for (i, page) in fixed.iter().enumerate() {
+                    let label = format!("{page}\n{} → {i}", position(page).unwrap());
+                    labels.insert(index[page], label);
+                }
+                format!("{} fixed to {}", join(update), join(&fixed))
+            }
+            Err(cycle) => format!("{} has no valid order. {cycle}", join(update)),
+        };
+        dot.push_str(&format!("    label={};\n", dot_id(&caption)));
+    }
+
+    for (i, page) in pages.iter().enumerate() {
+        match labels.get(&i) {
+            Some(label) => dot.push_str(&format!(
+                "    {} [label={}];\n",
+                dot_id(page),
+                dot_id(label)
+            )),
+            None => dot.push_str(&format!("    {};\n", dot_id(page))),
+        }
+    }
+    for &(a, b) in edges.iter() {
+        let style = if broken(a, b) {
+            " [color=red, penwidth=2]"
+        } else {
+            ""
+        };
+        dot.push_str(&format!(
+            "    {} -> {}{style};\n",
+            dot_id(&pages[a]),
+            dot_id(&pages[b])
+        ));
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn parse_pages<P: PageId>(pages: &str) -> Vec<P> {
+    pages
+        .split(',')
+        .map(|page| page.trim().parse().unwrap())
+        .collect()
+}
+
+// `pages` pages in a hidden order, each ruled before a few of the pages
+// shortly after it, and `updates` updates of 5 to 23 pages close to each other
+// in that order, every other one already sorted; for timing the pipeline
+fn generate_input(pages: usize, updates: usize) -> String {
+    const RULES_PER_PAGE: usize = 8;
+    const REACH: usize = 64;
+    assert!(pages > REACH, "Benchmarks need more than {REACH} pages");
+
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut random = |bound: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % bound as u64) as usize
+    };
+
+    let mut hidden: Vec<usize> = (0..pages).collect();
+    for i in (1..pages).rev() {
+        hidden.swap(i, random(i + 1));
+    }
+
+    let mut input = String::new();
+    for rank in 0..pages - 1 {
+        for _ in 0..RULES_PER_PAGE {
+            let later = rank + 1 + random(REACH.min(pages - rank - 1));
+            input.push_str(&format!("{}|{}\n", hidden[rank], hidden[later]));
+        }
+    }
+    input.push('\n');
+
+    for n in 0..updates {
+        let len = 5 + 2 * random(10);
+        let first = random(pages - REACH);
+        let mut ranks: Vec<usize> = vec![];
+        while ranks.len() < len {
+            let rank = first + random(REACH);
+            if !ranks.contains(&rank) {
+                ranks.push(rank);
+            }
+        }
+        if n % 2 == 0 {
+            ranks.sort_unstable();
+        }
+        let update: Vec<String> = ranks.iter().map(|&rank| hidden[rank].to_string()).collect();
+        input.push_str(&update.join(","));
+        input.push('\n');
+    }
+    input
+}
+
+// rule lines up to the blank line, leaving `reader` at the first update
+fn read_rules<R: BufRead>(reader: &mut R) -> std::io::Result<Vec<String>> {
+    let mut rules = vec![];
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            return Ok(rules);
+        }
+        rules.push(line.trim().to_string());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Outcome<P> {
+    Correct(Update<P>),
+    // the order `fix` gives
+    Fixed(Update<P>),
+    Unsortable(Cycle<P>),
+    // a line that isn't a list of pages; the stream goes on after it
+    Malformed(String),
+}
+
+// checks each update as soon as its line has been read, against rules read
+// before the first one
+struct UpdateStream<P, R> {
+    rules: Rules<P>,
+    lines: std::io::Lines<R>,
+    line: usize,
+}
+
+impl<P: PageId, R: BufRead> UpdateStream<P, R> {
+    fn new(rule_lines: &[String], reader: R) -> Self {
+        let rule_list: Vec<Rule<P>> = rule_lines.iter().map(|rule| parse_rule(rule)).collect();
+        UpdateStream {
+            rules: Rules::from_list(&rule_list),
+            lines: reader.lines(),
+            // the blank line
+            line: rule_lines.len() + 1,
+        }
+    }
+}
+
+impl<P: PageId, R: BufRead> Iterator for UpdateStream<P, R> {
+    // the line each update was on, and what became of it
+    type Item = std::io::Result<(usize, Outcome<P>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let text = match self.lines.next()? {
+                Ok(text) => text,
+                Err(err) => return Some(Err(err)),
+            };
+            self.line += 1;
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let update: Result<Update<P>, String> = text
+                .split(',')
+                .map(|page| {
+                    let page = page.trim();
+                    page.parse()
+                        .map_err(|err| format!("{page:?} is not a page ({err:?})"))
+                })
+                .collect();
+            let outcome = match update {
+                Err(reason) => Outcome::Malformed(reason),
+                Ok(update) if is_correct(&update, &self.rules) => Outcome::Correct(update),
+                Ok(update) => match fix(&update, &self.rules) {
+                    Ok(fixed) => Outcome::Fixed(fixed),
+                    Err(cycle) => Outcome::Unsortable(cycle),
+                },
+            };
+            return Some(Ok((self.line, outcome)));
+        }
+    }
+}
+
+// prints every update as it comes in, and the totals once the input ends
+fn run_stream<P: PageId, R: BufRead>(rule_lines: &[String], reader: R) {
+    // `None` once a middle page turns out not to be a number
+    let mut sums = [Some(0u64), Some(0u64)];
+    for verdict in UpdateStream::<P, R>::new(rule_lines, reader) {
+        let (line, outcome) = verdict.unwrap_or_else(|err| {
+            panic!("Updates could not be read because of an error: {:?}", err)
+        });
+        let (part, update) = match outcome {
+            Outcome::Correct(update) => (0, update),
+            Outcome::Fixed(update) => (1, update),
+            Outcome::Unsortable(cycle) => {
+                println!("{line}: no valid order. {cycle}");
+                continue;
+            }
+            Outcome::Malformed(reason) => {
+                println!("{line}: {reason}");
+                continue;
+            }
+        };
+        let middle = &update[update.len() / 2];
+        match part {
+            0 => println!("{line}: correct, middle page {middle}"),
+            _ => println!("{line}: fixed to {}, middle page {middle}", join(&update)),
+        }
+        sums[part] = sums[part]
+            .zip(middle.index())
+            .map(|(sum, page)| sum + page as u64);
     }
+
+    if let [Some(part_1_res), Some(part_2_res)] = sums {
+        println!("Part 1: {part_1_res}");
+        println!("Part 2: {part_2_res}");
+    }
+}
+
+fn join<P: Display>(pages: &[P]) -> String {
+    let pages: Vec<String> = pages.iter().map(P::to_string).collect();
+    pages.join(",")
+}
+
+fn middle<P: PageId>(update: &[P]) -> u64 {
+    let page = &update[update.len() / 2];
+    let number = page.index();
+    number.unwrap_or_else(|| panic!("Page {page} is not a number to add up")) as u64
 }
 
-fn part_1(input: &str) -> u32 {
-    let (rules, updates) = parse_input(input);
+fn part_1<P: PageId>(input: &str) -> u64 {
+    let (rules, updates) = parse_input::<P>(input);
     let mut middle_sum = 0;
 
     for update in updates.iter() {
         if is_correct(update, &rules) {
-            middle_sum += update[update.len() / 2] as u32;
+            middle_sum += middle(update);
         }
     }
 
     middle_sum
 }
 
-fn part_2(input: &str) -> u32 {
-    let (rules, updates) = parse_input(input);
+fn part_2<P: PageId>(input: &str) -> u64 {
+    let (rules, updates) = parse_input::<P>(input);
     let mut middle_sum = 0;
 
     for update in updates.iter() {
         if !is_correct(update, &rules) {
-            let mut fixed_update = update.clone();
-            fix(&mut fixed_update, &rules);
-            middle_sum += fixed_update[fixed_update.len() / 2] as u32;
+            let fixed_update = fix(update, &rules).unwrap_or_else(|cycle| panic!("{cycle}"));
+            middle_sum += middle(&fixed_update);
         }
     }
 
     middle_sum
 }
 
-fn main() {
-    let input = &std::fs::read_to_string(INPUT_PATH).unwrap();
+struct Options {
+    lint: bool,
+    must_precede: Option<String>,
+    orderings: Option<String>,
+    limit: usize,
+    explain: bool,
+    dot: bool,
+    // line of the update to draw
+    update: Option<usize>,
+    reduce: bool,
+    repair: Option<EditKind>,
+    bench: bool,
+}
+
+fn run<P: PageId>(input: &str, options: &Options) {
+    if options.bench {
+        let start = std::time::Instant::now();
+        let (rules, updates) = parse_input::<P>(input);
+        println!(
+            "Parsed {} updates in {:?}, {} rules",
+            updates.len(),
+            start.elapsed(),
+            if matches!(rules, Rules::Dense { .. }) {
+                "dense"
+            } else {
+                "sparse"
+            }
+        );
+        for (name, part) in [
+            ("Part 1", part_1::<P> as fn(&str) -> u64),
+            ("Part 2", part_2::<P>),
+        ] {
+            let start = std::time::Instant::now();
+            let res = part(input);
+            println!("{name}: {res} in {:?}", start.elapsed());
+        }
+        return;
+    }
+
+    if options.lint {
+        let (rule_list, updates) = parse_sections::<P>(input);
+        let lints = lint(&rule_list, &updates);
+        for lint in lints.iter() {
+            println!("{lint}");
+        }
+        println!("{} problem(s) found", lints.len());
+        return;
+    }
+
+    if let Some(kind) = options.repair {
+        let (rule_list, updates) = parse_sections::<P>(input);
+        let rules = Rules::from_list(&rule_list);
+        let first_update_line = rule_list.len() + 2;
+        let (mut repaired_count, mut edit_count) = (0, 0);
+        for (i, update) in updates.iter().enumerate() {
+            if is_correct(update, &rules) {
+                continue;
+            }
+            let line = first_update_line + i;
+            let repair = match kind {
+                EditKind::Moves => repair_by_moves(update, &rules).map_err(RepairError::from),
+                EditKind::Swaps => repair_by_swaps(update, &rules),
+            };
+            let repaired = repair.and_then(|repair| Ok((repair, fix(update, &rules)?)));
+            let (repair, fixed) = match repaired {
+                Ok(repaired) => repaired,
+                Err(err) => {
+                    println!("Update on line {line} {err}");
+                    continue;
+                }
+            };
+            println!(
+                "Update on line {line}: {} needs {} edit(s)",
+                join(update),
+                repair.edits.len()
+            );
+            for edit in repair.edits.iter() {
+                println!("    {edit}");
+            }
+            println!(
+                "    repaired to {}, middle page {} (fix gives {})",
+                join(&repair.repaired),
+                repair.repaired[repair.repaired.len() / 2],
+                fixed[fixed.len() / 2]
+            );
+            repaired_count += 1;
+            edit_count += repair.edits.len();
+        }
+        println!("{repaired_count} update(s) repaired with {edit_count} edit(s)");
+        return;
+    }
+
+    if options.dot {
+        let (rule_list, updates) = parse_sections::<P>(input);
+        let rules = Rules::from_list(&rule_list);
+        let update = options.update.map(|line| {
+            let first_update_line = rule_list.len() + 2;
+            line.checked_sub(first_update_line)
+                .and_then(|i| updates.get(i))
+                .unwrap_or_else(|| panic!("No update on line {line}"))
+                .as_slice()
+        });
+        print!("{}", to_dot(&rules, update, options.reduce));
+        return;
+    }
+
+    if options.must_precede.is_some() || options.orderings.is_some() || options.explain {
+        let (rule_list, updates) = parse_sections::<P>(input);
+        let graph = RuleGraph::new(&rule_list);
+
+        if let Some(pages) = &options.must_precede {
+            let pages: Vec<P> = parse_pages(pages);
+            let [a, b] = &pages[..] else {
+                panic!("--must-precede takes two pages, got {pages:?}");
+            };
+            println!("{a} must precede {b}: {}", graph.must_precede(a, b));
+        }
+
+        if let Some(pages) = &options.orderings {
+            let mut count = 0;
+            for ordering in graph
+                .all_valid_orderings(&parse_pages::<P>(pages))
+                .take(options.limit)
+            {
+                println!("{}", join(&ordering));
+                count += 1;
+            }
+            println!("{count} ordering(s) shown, at most {}", options.limit);
+        }
+
+        if options.explain {
+            let first_update_line = rule_list.len() + 2;
+            for (i, update) in updates.iter().enumerate() {
+                let violations = graph.explain(update);
+                if violations.is_empty() {
+                    continue;
+                }
+                let minimal = graph.minimal_violations(update);
+                println!("Update on line {}: {}", first_update_line + i, join(update));
+                for violation in violations.iter() {
+                    let (i, j) = violation.positions;
+                    println!(
+                        "    {} (at {j}) has to come before {} (at {i}), rule {}|{} on line {}{}",
+                        update[j],
+                        update[i],
+                        violation.rule.0,
+                        violation.rule.1,
+                        violation.line,
+                        if minimal.contains(violation) {
+                            ""
+                        } else {
+                            ", implied"
+                        }
+                    );
+                }
+            }
+        }
+        return;
+    }
 
-    let part_1_res = part_1(input);
+    let part_1_res = part_1::<P>(input);
     println!("Part 1: {part_1_res}");
 
-    let part_2_res = part_2(input);
+    let part_2_res = part_2::<P>(input);
     println!("Part 2: {part_2_res}");
 }
+
+fn main() {
+    let mut input_path = INPUT_PATH.to_string();
+    let mut options = Options {
+        lint: false,
+        must_precede: None,
+        orderings: None,
+        limit: 20,
+        explain: false,
+        dot: false,
+        update: None,
+        reduce: false,
+        repair: None,
+        bench: false,
+    };
+    let mut bench_size = None;
+    let mut stream = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input_path = args.next().expect("Missing value for --input"),
+            "--lint" => options.lint = true,
+            "--must-precede" => {
+                options.must_precede = Some(args.next().expect("Missing value for --must-precede"))
+            }
+            "--orderings" => {
+                options.orderings = Some(args.next().expect("Missing value for --orderings"))
+            }
+            "--limit" => {
+                let value = args.next().expect("Missing value for --limit");
+                options.limit = value.parse::<usize>().unwrap();
+            }
+            "--explain" => options.explain = true,
+            "--dot" => options.dot = true,
+            "--update" => {
+                let line = args.next().expect("Missing value for --update");
+                options.update = Some(line.parse::<usize>().unwrap());
+            }
+            "--reduce" => options.reduce = true,
+            "--stream" => stream = true,
+            "--bench" => {
+                let size = args.next().expect("Missing value for --bench");
+                let (pages, updates) = size
+                    .split_once(',')
+                    .expect("Expected --bench PAGES,UPDATES");
+                bench_size = Some((
+                    pages.parse::<usize>().unwrap(),
+                    updates.parse::<usize>().unwrap(),
+                ));
+            }
+            "--repair" => {
+                let kind = args.next().expect("Missing value for --repair");
+                options.repair = Some(match kind.as_str() {
+                    "moves" => EditKind::Moves,
+                    "swaps" => EditKind::Swaps,
+                    _ => panic!("Unknown edit kind: {kind}, expected moves or swaps"),
+                });
+            }
+            _ => panic!("Unexpected argument: {}", arg),
+        }
+    }
+
+    if stream {
+        let mut reader: Box<dyn BufRead> = if input_path == "-" {
+            Box::new(std::io::stdin().lock())
+        } else {
+            let file = std::fs::File::open(&input_path).unwrap_or_else(|err| {
+                panic!(
+                    "File {input_path} could not be oppened because of an error: {:?}",
+                    err
+                )
+            });
+            Box::new(BufReader::new(file))
+        };
+        let rule_lines = read_rules(&mut reader)
+            .unwrap_or_else(|err| panic!("Rules could not be read because of an error: {:?}", err));
+        // updates aren't kept, so numbers might as well be as wide as they get
+        match page_width(&rule_lines.join("\n")) {
+            Some(_) => run_stream::<u64, _>(&rule_lines, reader),
+            None => run_stream::<String, _>(&rule_lines, reader),
+        }
+        return;
+    }
+
+    let input = &match bench_size {
+        Some((pages, updates)) => {
+            options.bench = true;
+            generate_input(pages, updates)
+        }
+        None => std::fs::read_to_string(&input_path).unwrap(),
+    };
+
+    match page_width(input) {
+        Some(8) => run::<u8>(input, &options),
+        Some(16) => run::<u16>(input, &options),
+        Some(32) => run::<u32>(input, &options),
+        Some(_) => run::<u64>(input, &options),
+        None => run::<String>(input, &options),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xorshift, so the cases are the same on every run
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self, bound: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % bound
+        }
+
+        fn shuffled(&mut self, n: usize) -> Vec<u8> {
+            let mut pages: Vec<u8> = (0..n as u8).collect();
+            for i in (1..n).rev() {
+                pages.swap(i, self.next(i as u64 + 1) as usize);
+            }
+            pages
+        }
+    }
+
+    fn permutations(pages: &[u8]) -> Vec<Vec<u8>> {
+        if pages.is_empty() {
+            return vec![vec![]];
+        }
+        (0..pages.len())
+            .flat_map(|i| {
+                let mut rest = pages.to_vec();
+                let page = rest.remove(i);
+                permutations(&rest).into_iter().map(move |mut order| {
+                    order.insert(0, page);
+                    order
+                })
+            })
+            .collect()
+    }
+
+    // pairs of pages that `order` puts the other way round from `update`
+    fn inversions(update: &[u8], order: &[u8]) -> usize {
+        let position = |page| update.iter().position(|&p| p == page).unwrap();
+        (0..order.len())
+            .flat_map(|a| (a + 1..order.len()).map(move |b| (a, b)))
+            .filter(|&(a, b)| position(order[a]) > position(order[b]))
+            .count()
+    }
+
+    // pages that have to be moved to get from `update` to `order`: all but
+    // the longest run of pages the two keep in the same relative order
+    fn moves(update: &[u8], order: &[u8]) -> usize {
+        let mut common = vec![vec![0; order.len() + 1]; update.len() + 1];
+        for (i, a) in update.iter().enumerate() {
+            for (j, b) in order.iter().enumerate() {
+                common[i + 1][j + 1] = if a == b {
+                    common[i][j] + 1
+                } else {
+                    common[i][j + 1].max(common[i + 1][j])
+                };
+            }
+        }
+        update.len() - common[update.len()][order.len()]
+    }
+
+    fn replay(update: &[u8], edits: &[Edit<u8>]) -> Vec<u8> {
+        let mut current = update.to_vec();
+        for edit in edits {
+            match edit {
+                Edit::Move { page, from, to } => {
+                    assert_eq!(current[*from], *page);
+                    let page = current.remove(*from);
+                    current.insert(*to, page);
+                }
+                Edit::Swap { at, pages } => {
+                    assert_eq!((current[*at], current[at + 1]), *pages);
+                    current.swap(*at, at + 1);
+                }
+            }
+        }
+        current
+    }
+
+    #[test]
+    fn dense_and_sparse_rules_agree() {
+        let mut rng = Rng(777);
+        for case in 0..3000 {
+            // few pages, so rules repeat and pages rule themselves
+            let n = 1 + rng.next(6) as u8;
+            let rule_list: Vec<Rule<u8>> = (0..rng.next(12))
+                .map(|_| (rng.next(n as u64) as u8, rng.next(n as u64) as u8))
+                .collect();
+            let dense = Rules::from_list(&rule_list);
+            assert!(matches!(dense, Rules::Dense { .. }));
+            let mut sparse = Rules::Sparse(HashMap::new());
+            for (a, b) in rule_list.iter() {
+                sparse.insert(*a, *b);
+            }
+
+            let update: Vec<u8> = (0..rng.next(7))
+                .map(|_| rng.next(n as u64 + 1) as u8)
+                .collect();
+            assert_eq!(
+                is_correct(&update, &dense),
+                is_correct(&update, &sparse),
+                "case {case}: {update:?} {rule_list:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn tangles_need_a_cycle_of_three() {
+        let tangles = |rule_list: &[Rule<u8>]| {
+            lint(rule_list, &[])
+                .into_iter()
+                .filter_map(|lint| match lint {
+                    Lint::Tangle { cycle, .. } => Some(cycle.0),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            tangles(&[(1, 2), (2, 1), (2, 3), (3, 2), (3, 3)]),
+            vec![] as Vec<Vec<u8>>
+        );
+        assert_eq!(
+            tangles(&[(1, 2), (2, 1), (2, 3), (3, 2), (3, 1)]),
+            vec![vec![1, 2, 3]]
+        );
+        assert_eq!(
+            tangles(&[(1, 2), (2, 1), (1, 3), (3, 1), (2, 3), (3, 2)]),
+            vec![vec![1, 2, 3]]
+        );
+    }
+
+    #[test]
+    fn repairs_match_brute_force() {
+        let mut rng = Rng(12345);
+        for case in 0..3000 {
+            // rules that follow a hidden order, so there is always a valid one
+            let n = 1 + rng.next(7) as usize;
+            let hidden = rng.shuffled(n);
+            let density = rng.next(100);
+            let mut rule_list = vec![];
+            for i in 0..n {
+                for j in i + 1..n {
+                    if rng.next(100) < density {
+                        rule_list.push((hidden[i], hidden[j]));
+                    }
+                }
+            }
+            let rules = Rules::from_list(&rule_list);
+            let update = rng.shuffled(n);
+
+            let valid: Vec<Vec<u8>> = permutations(&update)
+                .into_iter()
+                .filter(|order| is_correct(order, &rules))
+                .collect();
+            let fewest = |edits: fn(&[u8], &[u8]) -> usize| {
+                valid
+                    .iter()
+                    .map(|order| edits(&update, order))
+                    .min()
+                    .unwrap()
+            };
+            let repairs = [
+                (repair_by_moves(&update, &rules).unwrap(), fewest(moves)),
+                (
+                    repair_by_swaps(&update, &rules).unwrap(),
+                    fewest(inversions),
+                ),
+            ];
+            for (repair, expected) in repairs {
+                let context = format!("case {case}: {update:?} {rule_list:?}");
+                assert_eq!(replay(&update, &repair.edits), repair.repaired, "{context}");
+                assert!(is_correct(&repair.repaired, &rules), "{context}");
+                assert_eq!(repair.edits.len(), expected, "{context}");
+            }
+        }
+    }
+
+    #[test]
+    fn large_updates_are_not_repaired_by_swaps() {
+        let rules = Rules::from_list(&[(1u8, 0u8)]);
+        for n in [22, 65] {
+            let update: Vec<u8> = (0..n).collect();
+            assert_eq!(
+                repair_by_swaps(&update, &rules),
+                Err(RepairError::TooLarge { pages: n as usize })
+            );
+            assert_eq!(repair_by_moves(&update, &rules).unwrap().edits.len(), 1);
+        }
+    }
+}
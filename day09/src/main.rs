@@ -50,12 +50,11 @@ fn next_block<T: PartialEq>(disk: &[T], start: usize, step: i32) -> Option<(&[T]
         p = (p as i32 + step) as usize;
     }
 
-    let res;
-    if start <= p {
-        res = (&disk[start..p], p)
+    let res = if start <= p {
+        (&disk[start..p], p)
     } else {
-        res = (&disk[p + 1..=start], p)
-    }
+        (&disk[p + 1..=start], p)
+    };
 
     if res.0.is_empty() {
         None
@@ -66,7 +65,7 @@ fn next_block<T: PartialEq>(disk: &[T], start: usize, step: i32) -> Option<(&[T]
 
 fn next_free<T: Clone + PartialEq>(disk: &[Option<T>], start: usize) -> Option<(&[Option<T>], usize)> {
     let mut p = start;
-    while let Some((block, p1)) = next_block(&disk, p, -1) {
+    while let Some((block, p1)) = next_block(disk, p, -1) {
         if block.is_file() {
             return Some((block, p));
         }